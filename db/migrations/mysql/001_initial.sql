@@ -1,13 +1,19 @@
-DROP DATABASE IF EXISTS `iota`;
-CREATE DATABASE `iota` /*!40100 DEFAULT CHARACTER SET utf8 */;
-USE `iota`;
+--
+-- Table structure for table `schema_version`
+--
+
+CREATE TABLE IF NOT EXISTS `{prefix}schema_version` (
+  `version` int(11) NOT NULL,
+  `name` varchar(255) NOT NULL,
+  `applied` timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (`version`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
 
 --
 -- Table structure for table `address`
 --
 
-DROP TABLE IF EXISTS `address`;
-CREATE TABLE `address` (
+CREATE TABLE IF NOT EXISTS `{prefix}address` (
   `id_address` int(11) NOT NULL UNIQUE,
   `address` char(81) NOT NULL,
   `checksum` char(9) DEFAULT NULL,
@@ -19,8 +25,7 @@ CREATE TABLE `address` (
 -- Table structure for table `bundle`
 --
 
-DROP TABLE IF EXISTS `bundle`;
-CREATE TABLE `bundle` (
+CREATE TABLE IF NOT EXISTS `{prefix}bundle` (
   `id_bundle` int(11) NOT NULL UNIQUE,
   `bundle` char(81) NOT NULL,
   `is_mst` char(1) DEFAULT '0',
@@ -33,8 +38,7 @@ CREATE TABLE `bundle` (
 -- Table structure for table `txload`
 --
 
-DROP TABLE IF EXISTS `txload`;
-CREATE TABLE `txload` (
+CREATE TABLE IF NOT EXISTS `{prefix}txload` (
   `id_txload` int(11) NOT NULL AUTO_INCREMENT,
   `event` char(3) NOT NULL,
   `count` int(11) NOT NULL DEFAULT 1,
@@ -48,8 +52,7 @@ CREATE TABLE `txload` (
 -- Table structure for table `tx`
 --
 
-DROP TABLE IF EXISTS `tx`;
-CREATE TABLE `tx` (
+CREATE TABLE IF NOT EXISTS `{prefix}tx` (
   `id_tx` int(11) NOT NULL UNIQUE,
   `hash` char(81) NOT NULL,
   `id_trunk` int(11) DEFAULT NULL,
@@ -0,0 +1,29 @@
+--
+-- Table structure for table `node_milestone`
+--
+
+CREATE TABLE IF NOT EXISTS `{prefix}node_milestone` (
+  `id_node_milestone` int(11) NOT NULL AUTO_INCREMENT,
+  `topic` char(4) NOT NULL,
+  `previous_idx` int(11) DEFAULT NULL,
+  `milestone_idx` int(11) DEFAULT NULL,
+  `hash` char(81) DEFAULT NULL,
+  `timestamp` double NOT NULL,
+  PRIMARY KEY (`id_node_milestone`),
+  KEY `topic_INDEX` (`topic`),
+  KEY `timestamp_INDEX` (`timestamp`)
+) ENGINE=InnoDB AUTO_INCREMENT=1 DEFAULT CHARSET=utf8;
+
+--
+-- Table structure for table `node_confirmation`
+--
+
+CREATE TABLE IF NOT EXISTS `{prefix}node_confirmation` (
+  `id_node_confirmation` int(11) NOT NULL AUTO_INCREMENT,
+  `hash` char(81) NOT NULL,
+  `milestone_idx` int(11) NOT NULL,
+  `timestamp` double NOT NULL,
+  PRIMARY KEY (`id_node_confirmation`),
+  KEY `hash_INDEX` (`hash`),
+  KEY `milestone_idx_INDEX` (`milestone_idx`)
+) ENGINE=InnoDB AUTO_INCREMENT=1 DEFAULT CHARSET=utf8;
@@ -0,0 +1,14 @@
+--
+-- Table structure for table `tx_payload`
+--
+
+CREATE TABLE IF NOT EXISTS `{prefix}tx_payload` (
+  `id_tx` int(11) NOT NULL,
+  `signature_message_fragment` text NOT NULL,
+  `obsolete_tag` char(27) DEFAULT NULL,
+  `nonce` char(27) DEFAULT NULL,
+  `attachment_timestamp` bigint(20) DEFAULT '0',
+  `attachment_timestamp_lower` bigint(20) DEFAULT '0',
+  `attachment_timestamp_upper` bigint(20) DEFAULT '0',
+  PRIMARY KEY (`id_tx`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
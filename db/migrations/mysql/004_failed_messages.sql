@@ -0,0 +1,13 @@
+--
+-- Table structure for table `failed_messages`
+--
+
+CREATE TABLE IF NOT EXISTS `{prefix}failed_messages` (
+  `id_failed_message` int(11) NOT NULL AUTO_INCREMENT,
+  `message` text NOT NULL,
+  `stage` varchar(16) NOT NULL,
+  `error` text NOT NULL,
+  `timestamp` double NOT NULL,
+  PRIMARY KEY (`id_failed_message`),
+  KEY `stage_INDEX` (`stage`)
+) ENGINE=InnoDB AUTO_INCREMENT=1 DEFAULT CHARSET=utf8;
@@ -0,0 +1,11 @@
+--
+-- Table structure for table `job_queue`
+--
+
+CREATE TABLE IF NOT EXISTS `{prefix}job_queue` (
+  `id_job` bigint(20) NOT NULL,
+  `kind` varchar(16) NOT NULL,
+  `data` varchar(255) NOT NULL,
+  PRIMARY KEY (`id_job`),
+  KEY `kind_INDEX` (`kind`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8;
@@ -0,0 +1,84 @@
+--
+-- Table structure for table `schema_version`
+--
+
+CREATE TABLE IF NOT EXISTS {prefix}schema_version (
+  version integer NOT NULL,
+  name varchar(255) NOT NULL,
+  applied timestamp NOT NULL DEFAULT now(),
+  PRIMARY KEY (version)
+);
+
+--
+-- Table structure for table `address`
+--
+
+CREATE TABLE IF NOT EXISTS {prefix}address (
+  id_address integer NOT NULL UNIQUE,
+  address char(81) NOT NULL,
+  checksum char(9) DEFAULT NULL,
+  PRIMARY KEY (id_address),
+  CONSTRAINT {prefix}address_UNIQUE UNIQUE (address)
+);
+
+--
+-- Table structure for table `bundle`
+--
+
+CREATE TABLE IF NOT EXISTS {prefix}bundle (
+  id_bundle integer NOT NULL UNIQUE,
+  bundle char(81) NOT NULL,
+  is_mst char(1) DEFAULT '0',
+  PRIMARY KEY (id_bundle)
+);
+CREATE INDEX IF NOT EXISTS {prefix}bundle_INDEX ON {prefix}bundle (bundle);
+CREATE INDEX IF NOT EXISTS {prefix}bundle_is_mst_INDEX ON {prefix}bundle (is_mst);
+
+--
+-- Table structure for table `txload`
+--
+
+CREATE TABLE IF NOT EXISTS {prefix}txload (
+  id_txload serial NOT NULL,
+  event char(3) NOT NULL,
+  count integer NOT NULL DEFAULT 1,
+  timestamp double precision NOT NULL,
+  PRIMARY KEY (id_txload)
+);
+CREATE INDEX IF NOT EXISTS {prefix}event_INDEX ON {prefix}txload (event);
+CREATE INDEX IF NOT EXISTS {prefix}timestamp_INDEX ON {prefix}txload (timestamp);
+
+--
+-- Table structure for table `tx`
+--
+
+CREATE TABLE IF NOT EXISTS {prefix}tx (
+  id_tx integer NOT NULL UNIQUE,
+  hash char(81) NOT NULL,
+  id_trunk integer DEFAULT NULL,
+  id_branch integer DEFAULT NULL,
+  id_address integer DEFAULT NULL,
+  id_bundle integer DEFAULT NULL,
+  tag char(27) DEFAULT NULL,
+  value bigint DEFAULT '0',
+  timestamp double precision DEFAULT '0',
+  arrival double precision DEFAULT '0',
+  conftime double precision DEFAULT '0',
+  current_idx integer DEFAULT NULL,
+  last_idx integer DEFAULT NULL,
+  da integer DEFAULT '0',
+  height integer DEFAULT '0',
+  weight double precision DEFAULT '0',
+  is_mst char(1) DEFAULT '0',
+  mst_a char(1) DEFAULT '0',
+  solid char(1) DEFAULT '0',
+  PRIMARY KEY (id_tx),
+  CONSTRAINT {prefix}hash_UNIQUE UNIQUE (hash)
+);
+CREATE INDEX IF NOT EXISTS {prefix}id_trunk_INDEX ON {prefix}tx (id_trunk);
+CREATE INDEX IF NOT EXISTS {prefix}id_branch_INDEX ON {prefix}tx (id_branch);
+CREATE INDEX IF NOT EXISTS {prefix}id_address_INDEX ON {prefix}tx (id_address);
+CREATE INDEX IF NOT EXISTS {prefix}id_bundle_INDEX ON {prefix}tx (id_bundle);
+CREATE INDEX IF NOT EXISTS {prefix}da_INDEX ON {prefix}tx (da);
+CREATE INDEX IF NOT EXISTS {prefix}is_mst_INDEX ON {prefix}tx (is_mst);
+CREATE INDEX IF NOT EXISTS {prefix}mst_a_INDEX ON {prefix}tx (mst_a);
@@ -0,0 +1,33 @@
+--
+-- Table structure for table `node_milestone`
+--
+
+CREATE TABLE IF NOT EXISTS {prefix}node_milestone (
+  id_node_milestone serial NOT NULL,
+  topic char(4) NOT NULL,
+  previous_idx integer DEFAULT NULL,
+  milestone_idx integer DEFAULT NULL,
+  hash char(81) DEFAULT NULL,
+  timestamp double precision NOT NULL,
+  PRIMARY KEY (id_node_milestone)
+);
+CREATE INDEX IF NOT EXISTS {prefix}node_milestone_topic_INDEX
+  ON {prefix}node_milestone (topic);
+CREATE INDEX IF NOT EXISTS {prefix}node_milestone_timestamp_INDEX
+  ON {prefix}node_milestone (timestamp);
+
+--
+-- Table structure for table `node_confirmation`
+--
+
+CREATE TABLE IF NOT EXISTS {prefix}node_confirmation (
+  id_node_confirmation serial NOT NULL,
+  hash char(81) NOT NULL,
+  milestone_idx integer NOT NULL,
+  timestamp double precision NOT NULL,
+  PRIMARY KEY (id_node_confirmation)
+);
+CREATE INDEX IF NOT EXISTS {prefix}node_confirmation_hash_INDEX
+  ON {prefix}node_confirmation (hash);
+CREATE INDEX IF NOT EXISTS {prefix}node_confirmation_milestone_idx_INDEX
+  ON {prefix}node_confirmation (milestone_idx);
@@ -0,0 +1,14 @@
+--
+-- Table structure for table `tx_payload`
+--
+
+CREATE TABLE IF NOT EXISTS {prefix}tx_payload (
+  id_tx integer NOT NULL,
+  signature_message_fragment char(2187) NOT NULL,
+  obsolete_tag char(27) DEFAULT NULL,
+  nonce char(27) DEFAULT NULL,
+  attachment_timestamp bigint DEFAULT '0',
+  attachment_timestamp_lower bigint DEFAULT '0',
+  attachment_timestamp_upper bigint DEFAULT '0',
+  PRIMARY KEY (id_tx)
+);
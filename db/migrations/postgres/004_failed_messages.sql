@@ -0,0 +1,14 @@
+--
+-- Table structure for table `failed_messages`
+--
+
+CREATE TABLE IF NOT EXISTS {prefix}failed_messages (
+  id_failed_message serial NOT NULL,
+  message text NOT NULL,
+  stage varchar(16) NOT NULL,
+  error text NOT NULL,
+  timestamp double precision NOT NULL,
+  PRIMARY KEY (id_failed_message)
+);
+CREATE INDEX IF NOT EXISTS {prefix}failed_messages_stage_INDEX
+  ON {prefix}failed_messages (stage);
@@ -0,0 +1,12 @@
+--
+-- Table structure for table `job_queue`
+--
+
+CREATE TABLE IF NOT EXISTS {prefix}job_queue (
+  id_job bigint NOT NULL,
+  kind varchar(16) NOT NULL,
+  data varchar(255) NOT NULL,
+  PRIMARY KEY (id_job)
+);
+CREATE INDEX IF NOT EXISTS {prefix}job_queue_kind_INDEX
+  ON {prefix}job_queue (kind);
@@ -0,0 +1,81 @@
+--
+-- Table structure for table `schema_version`
+--
+
+CREATE TABLE IF NOT EXISTS {prefix}schema_version (
+  version INTEGER NOT NULL,
+  name VARCHAR(255) NOT NULL,
+  applied TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (version)
+);
+
+--
+-- Table structure for table `address`
+--
+
+CREATE TABLE IF NOT EXISTS {prefix}address (
+  id_address INTEGER NOT NULL UNIQUE,
+  address CHAR(81) NOT NULL UNIQUE,
+  checksum CHAR(9) DEFAULT NULL,
+  PRIMARY KEY (id_address)
+);
+
+--
+-- Table structure for table `bundle`
+--
+
+CREATE TABLE IF NOT EXISTS {prefix}bundle (
+  id_bundle INTEGER NOT NULL UNIQUE,
+  bundle CHAR(81) NOT NULL,
+  is_mst CHAR(1) DEFAULT '0',
+  PRIMARY KEY (id_bundle)
+);
+CREATE INDEX IF NOT EXISTS {prefix}bundle_INDEX ON {prefix}bundle (bundle);
+CREATE INDEX IF NOT EXISTS {prefix}bundle_is_mst_INDEX ON {prefix}bundle (is_mst);
+
+--
+-- Table structure for table `txload`
+--
+
+CREATE TABLE IF NOT EXISTS {prefix}txload (
+  id_txload INTEGER PRIMARY KEY AUTOINCREMENT,
+  event CHAR(3) NOT NULL,
+  count INTEGER NOT NULL DEFAULT 1,
+  timestamp REAL NOT NULL
+);
+CREATE INDEX IF NOT EXISTS {prefix}event_INDEX ON {prefix}txload (event);
+CREATE INDEX IF NOT EXISTS {prefix}timestamp_INDEX ON {prefix}txload (timestamp);
+
+--
+-- Table structure for table `tx`
+--
+
+CREATE TABLE IF NOT EXISTS {prefix}tx (
+  id_tx INTEGER NOT NULL UNIQUE,
+  hash CHAR(81) NOT NULL UNIQUE,
+  id_trunk INTEGER DEFAULT NULL,
+  id_branch INTEGER DEFAULT NULL,
+  id_address INTEGER DEFAULT NULL,
+  id_bundle INTEGER DEFAULT NULL,
+  tag CHAR(27) DEFAULT NULL,
+  value INTEGER DEFAULT '0',
+  timestamp REAL DEFAULT '0',
+  arrival REAL DEFAULT '0',
+  conftime REAL DEFAULT '0',
+  current_idx INTEGER DEFAULT NULL,
+  last_idx INTEGER DEFAULT NULL,
+  da INTEGER DEFAULT '0',
+  height INTEGER DEFAULT '0',
+  weight REAL DEFAULT '0',
+  is_mst CHAR(1) DEFAULT '0',
+  mst_a CHAR(1) DEFAULT '0',
+  solid CHAR(1) DEFAULT '0',
+  PRIMARY KEY (id_tx)
+);
+CREATE INDEX IF NOT EXISTS {prefix}id_trunk_INDEX ON {prefix}tx (id_trunk);
+CREATE INDEX IF NOT EXISTS {prefix}id_branch_INDEX ON {prefix}tx (id_branch);
+CREATE INDEX IF NOT EXISTS {prefix}id_address_INDEX ON {prefix}tx (id_address);
+CREATE INDEX IF NOT EXISTS {prefix}id_bundle_INDEX ON {prefix}tx (id_bundle);
+CREATE INDEX IF NOT EXISTS {prefix}da_INDEX ON {prefix}tx (da);
+CREATE INDEX IF NOT EXISTS {prefix}is_mst_INDEX ON {prefix}tx (is_mst);
+CREATE INDEX IF NOT EXISTS {prefix}mst_a_INDEX ON {prefix}tx (mst_a);
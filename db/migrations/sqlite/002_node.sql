@@ -0,0 +1,31 @@
+--
+-- Table structure for table `node_milestone`
+--
+
+CREATE TABLE IF NOT EXISTS {prefix}node_milestone (
+  id_node_milestone INTEGER PRIMARY KEY AUTOINCREMENT,
+  topic CHAR(4) NOT NULL,
+  previous_idx INTEGER DEFAULT NULL,
+  milestone_idx INTEGER DEFAULT NULL,
+  hash CHAR(81) DEFAULT NULL,
+  timestamp REAL NOT NULL
+);
+CREATE INDEX IF NOT EXISTS {prefix}node_milestone_topic_INDEX
+  ON {prefix}node_milestone (topic);
+CREATE INDEX IF NOT EXISTS {prefix}node_milestone_timestamp_INDEX
+  ON {prefix}node_milestone (timestamp);
+
+--
+-- Table structure for table `node_confirmation`
+--
+
+CREATE TABLE IF NOT EXISTS {prefix}node_confirmation (
+  id_node_confirmation INTEGER PRIMARY KEY AUTOINCREMENT,
+  hash CHAR(81) NOT NULL,
+  milestone_idx INTEGER NOT NULL,
+  timestamp REAL NOT NULL
+);
+CREATE INDEX IF NOT EXISTS {prefix}node_confirmation_hash_INDEX
+  ON {prefix}node_confirmation (hash);
+CREATE INDEX IF NOT EXISTS {prefix}node_confirmation_milestone_idx_INDEX
+  ON {prefix}node_confirmation (milestone_idx);
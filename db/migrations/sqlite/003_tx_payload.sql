@@ -0,0 +1,13 @@
+--
+-- Table structure for table `tx_payload`
+--
+
+CREATE TABLE IF NOT EXISTS {prefix}tx_payload (
+  id_tx INTEGER PRIMARY KEY,
+  signature_message_fragment CHAR(2187) NOT NULL,
+  obsolete_tag CHAR(27) DEFAULT NULL,
+  nonce CHAR(27) DEFAULT NULL,
+  attachment_timestamp INTEGER DEFAULT 0,
+  attachment_timestamp_lower INTEGER DEFAULT 0,
+  attachment_timestamp_upper INTEGER DEFAULT 0
+);
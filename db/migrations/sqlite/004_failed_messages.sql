@@ -0,0 +1,13 @@
+--
+-- Table structure for table `failed_messages`
+--
+
+CREATE TABLE IF NOT EXISTS {prefix}failed_messages (
+  id_failed_message INTEGER PRIMARY KEY AUTOINCREMENT,
+  message TEXT NOT NULL,
+  stage VARCHAR(16) NOT NULL,
+  error TEXT NOT NULL,
+  timestamp REAL NOT NULL
+);
+CREATE INDEX IF NOT EXISTS {prefix}failed_messages_stage_INDEX
+  ON {prefix}failed_messages (stage);
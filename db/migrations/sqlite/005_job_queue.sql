@@ -0,0 +1,11 @@
+--
+-- Table structure for table `job_queue`
+--
+
+CREATE TABLE IF NOT EXISTS {prefix}job_queue (
+  id_job INTEGER PRIMARY KEY,
+  kind VARCHAR(16) NOT NULL,
+  data VARCHAR(255) NOT NULL
+);
+CREATE INDEX IF NOT EXISTS {prefix}job_queue_kind_INDEX
+  ON {prefix}job_queue (kind);
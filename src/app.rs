@@ -1,10 +1,23 @@
-use clap::{App, Arg};
+use clap::{App, AppSettings, Arg, SubCommand};
 
+const DEFAULT_DATABASE: &str = "iota";
 const DEFAULT_RETRY_INTERVAL: &str = "1000";
+const DEFAULT_ZMQ_TIMEOUT: &str = "60000";
 const DEFAULT_UPDATE_INTERVAL: &str = "1000";
+const DEFAULT_FLUSH_THRESHOLD: &str = "0";
+const DEFAULT_FLUSH_AGE: &str = "0";
 const DEFAULT_CALCULATION_THREADS: &str = "1";
 const DEFAULT_CALCULATION_LIMIT: &str = "1000";
 const DEFAULT_GENERATION_LIMIT: &str = "10";
+const DEFAULT_BATCH_SIZE: &str = "500";
+const DEFAULT_CACHE_CAPACITY: &str = "0";
+const DEFAULT_WARM_UP: &str = "10000";
+const DEFAULT_QUEUE_CAPACITY: &str = "10000";
+const DEFAULT_CALCULATE_OVERFLOW: &str = "block";
+const DEFAULT_RESTART_LIMIT: &str = "10";
+const DEFAULT_RESTART_WINDOW: &str = "600000";
+const DEFAULT_READY_IDLE: &str = "60000";
+const DEFAULT_READY_QUEUE: &str = "90";
 const DEFAULT_MILESTONE_ADDRESS: &str =
   "KPWCHICGJZXKE9GSUDXZYUAPLHAKAHYHDXNPHENTE\
    RYMMBQOPSQIDENXKLKCEYCPVTZQLEEJVYJZV9BWU";
@@ -13,94 +26,353 @@ const DEFAULT_LOG_CONFIG: &str = "log4rs.yaml";
 
 pub fn build<'a, 'b>() -> App<'a, 'b> {
   app_from_crate!()
+    .setting(AppSettings::SubcommandsNegateReqs)
+    .subcommand(
+      SubCommand::with_name("migrate")
+        .about("Applies pending database schema migrations")
+        .arg(storage_uri())
+        .arg(database())
+        .arg(table_prefix())
+        .arg(retry_interval())
+        .arg(log_config()),
+    )
+    .subcommand(
+      SubCommand::with_name("retry-dead-letters")
+        .about("Re-processes messages stored in the `failed_messages` table")
+        .arg(storage_uri())
+        .arg(database())
+        .arg(table_prefix())
+        .arg(retry_interval())
+        .arg(calculation_limit())
+        .arg(batch_size())
+        .arg(milestone_address())
+        .arg(milestone_start_index())
+        .arg(log_config()),
+    )
+    .subcommand(
+      SubCommand::with_name("replay")
+        .about("Feeds a recorded ZMQ message file through the pipeline")
+        .arg(
+          Arg::with_name("replay_file")
+            .index(1)
+            .value_name("FILE")
+            .required(true)
+            .help("File written by the --record option"),
+        )
+        .arg(
+          Arg::with_name("paced")
+            .long("paced")
+            .help("Replay messages at their original pacing"),
+        )
+        .args(&pipeline()),
+    )
     .arg(
       Arg::with_name("zmq_uri")
         .short("z")
         .long("zmq")
         .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
         .value_name("URI")
         .required(true)
-        .help("ZMQ source server URI"),
-    )
-    .arg(
-      Arg::with_name("mysql_uri")
-        .short("m")
-        .long("mysql")
-        .takes_value(true)
-        .value_name("URI")
-        .required(true)
-        .help("MySQL destination server URI"),
-    )
-    .arg(
-      Arg::with_name("retry_interval")
-        .short("r")
-        .long("retry-interval")
-        .takes_value(true)
-        .value_name("INTERVAL")
-        .default_value(DEFAULT_RETRY_INTERVAL)
-        .help("MySQL connect retry interval in milliseconds"),
-    )
-    .arg(
-      Arg::with_name("update_interval")
-        .short("u")
-        .long("update-interval")
-        .takes_value(true)
-        .value_name("INTERVAL")
-        .default_value(DEFAULT_UPDATE_INTERVAL)
-        .help("MySQL update interval in milliseconds"),
-    )
-    .arg(
-      Arg::with_name("calculation_threads")
-        .short("T")
-        .long("calculation-threads")
-        .takes_value(true)
-        .value_name("THREADS")
-        .default_value(DEFAULT_CALCULATION_THREADS)
-        .help("Number of calculation threads"),
-    )
-    .arg(
-      Arg::with_name("calculation_limit")
-        .short("t")
-        .long("calculation-limit")
-        .takes_value(true)
-        .value_name("LIMIT")
-        .default_value(DEFAULT_CALCULATION_LIMIT)
-        .help("Calculation depth limit"),
-    )
-    .arg(
-      Arg::with_name("generation_limit")
-        .short("g")
-        .long("generation-limit")
-        .takes_value(true)
-        .value_name("LIMIT")
-        .default_value(DEFAULT_GENERATION_LIMIT)
-        .help("Garbage collector generation limit"),
+        .help(
+          "ZMQ source server URI, repeat to merge several redundant sources",
+        ),
     )
     .arg(
-      Arg::with_name("milestone_address")
-        .short("M")
-        .long("milestone-address")
-        .takes_value(true)
-        .value_name("ADDRESS")
-        .default_value(DEFAULT_MILESTONE_ADDRESS)
-        .help("Milestone address"),
+      Arg::with_name("trytes")
+        .long("trytes")
+        .help(
+          "Ingest raw `tx_trytes` messages instead of `tx` in order to store \
+           full transactions",
+        ),
     )
     .arg(
-      Arg::with_name("milestone_start_index")
-        .short("I")
-        .long("milestone-start-index")
+      Arg::with_name("zmq_timeout")
+        .long("zmq-timeout")
         .takes_value(true)
-        .value_name("INDEX")
-        .default_value(DEFAULT_MILESTONE_START_INDEX)
-        .help("Milestone start index"),
+        .value_name("TIMEOUT")
+        .default_value(DEFAULT_ZMQ_TIMEOUT)
+        .help(
+          "Resubscribe to a ZMQ source silent for longer than the timeout \
+           in milliseconds (0 disables)",
+        ),
     )
     .arg(
-      Arg::with_name("log_config")
-        .short("C")
-        .long("log-config")
+      Arg::with_name("record")
+        .long("record")
         .takes_value(true)
         .value_name("FILE")
-        .default_value(DEFAULT_LOG_CONFIG)
-        .help("Path to log4rs configuration file"),
+        .help("Append received ZMQ messages with arrival time to the file"),
     )
+    .args(&pipeline())
+}
+
+fn pipeline<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+  vec![
+    storage_uri(),
+    database(),
+    table_prefix(),
+    retry_interval(),
+    Arg::with_name("migrate")
+      .long("migrate")
+      .help("Apply pending database schema migrations on startup"),
+    Arg::with_name("update_interval")
+      .short("u")
+      .long("update-interval")
+      .takes_value(true)
+      .value_name("INTERVAL")
+      .default_value(DEFAULT_UPDATE_INTERVAL)
+      .help("Database update interval in milliseconds"),
+    Arg::with_name("flush_threshold")
+      .long("flush-threshold")
+      .takes_value(true)
+      .value_name("COUNT")
+      .default_value(DEFAULT_FLUSH_THRESHOLD)
+      .help(
+        "Update the database early once this many records are modified \
+         (0 disables)",
+      ),
+    Arg::with_name("flush_age")
+      .long("flush-age")
+      .takes_value(true)
+      .value_name("AGE")
+      .default_value(DEFAULT_FLUSH_AGE)
+      .help(
+        "Update the database early once a record is modified for longer \
+         than the age in milliseconds (0 disables)",
+      ),
+    Arg::with_name("calculation_threads")
+      .short("T")
+      .long("calculation-threads")
+      .takes_value(true)
+      .value_name("THREADS")
+      .default_value(DEFAULT_CALCULATION_THREADS)
+      .help("Number of calculation threads"),
+    calculation_limit(),
+    Arg::with_name("generation_limit")
+      .short("g")
+      .long("generation-limit")
+      .takes_value(true)
+      .value_name("LIMIT")
+      .default_value(DEFAULT_GENERATION_LIMIT)
+      .help("Garbage collector generation limit"),
+    batch_size(),
+    Arg::with_name("warm_up")
+      .long("warm-up")
+      .takes_value(true)
+      .value_name("COUNT")
+      .default_value(DEFAULT_WARM_UP)
+      .help(
+        "Number of the newest transactions loaded into the cache on startup \
+         (0 disables)",
+      ),
+    cache_capacity(
+      "transaction_cache",
+      "transaction-cache",
+      "Maximum number of cached transactions (0 is unlimited)",
+    ),
+    cache_capacity(
+      "address_cache",
+      "address-cache",
+      "Maximum number of cached addresses (0 is unlimited)",
+    ),
+    cache_capacity(
+      "bundle_cache",
+      "bundle-cache",
+      "Maximum number of cached bundles (0 is unlimited)",
+    ),
+    queue_capacity(
+      "insert_queue",
+      "insert-queue",
+      "Capacity of the incoming message queue",
+    ),
+    queue_capacity(
+      "node_queue",
+      "node-queue",
+      "Capacity of the node message queue",
+    ),
+    queue_capacity(
+      "approve_queue",
+      "approve-queue",
+      "Capacity of the approve job queue",
+    ),
+    queue_capacity(
+      "solidate_queue",
+      "solidate-queue",
+      "Capacity of the solidate job queue",
+    ),
+    queue_capacity(
+      "calculate_queue",
+      "calculate-queue",
+      "Capacity of the calculate job queue",
+    ),
+    Arg::with_name("calculate_overflow")
+      .long("calculate-overflow")
+      .takes_value(true)
+      .value_name("POLICY")
+      .possible_values(&["block", "coalesce", "drop"])
+      .default_value(DEFAULT_CALCULATE_OVERFLOW)
+      .help("What to do with calculate jobs when the queue is full"),
+    Arg::with_name("restart_limit")
+      .long("restart-limit")
+      .takes_value(true)
+      .value_name("LIMIT")
+      .default_value(DEFAULT_RESTART_LIMIT)
+      .help(
+        "Exit after this many restarts of one worker thread within the \
+         restart window",
+      ),
+    Arg::with_name("restart_window")
+      .long("restart-window")
+      .takes_value(true)
+      .value_name("INTERVAL")
+      .default_value(DEFAULT_RESTART_WINDOW)
+      .help("Interval in milliseconds over which restarts are counted"),
+    Arg::with_name("http")
+      .long("http")
+      .takes_value(true)
+      .value_name("ADDRESS")
+      .help(
+        "Serve Prometheus metrics at /metrics and health checks at /healthz \
+         and /readyz on the address",
+      ),
+    Arg::with_name("ready_idle")
+      .long("ready-idle")
+      .takes_value(true)
+      .value_name("INTERVAL")
+      .default_value(DEFAULT_READY_IDLE)
+      .help(
+        "Report not ready when no message is received for the interval in \
+         milliseconds (0 disables)",
+      ),
+    Arg::with_name("ready_queue")
+      .long("ready-queue")
+      .takes_value(true)
+      .value_name("PERCENT")
+      .default_value(DEFAULT_READY_QUEUE)
+      .help(
+        "Report not ready when a queue is filled to the percentage \
+         (0 disables)",
+      ),
+    milestone_address(),
+    milestone_start_index(),
+    log_config(),
+  ]
+}
+
+fn queue_capacity<'a, 'b>(
+  name: &'a str,
+  long: &'a str,
+  help: &'b str,
+) -> Arg<'a, 'b> {
+  Arg::with_name(name)
+    .long(long)
+    .takes_value(true)
+    .value_name("CAPACITY")
+    .default_value(DEFAULT_QUEUE_CAPACITY)
+    .help(help)
+}
+
+fn cache_capacity<'a, 'b>(
+  name: &'a str,
+  long: &'a str,
+  help: &'b str,
+) -> Arg<'a, 'b> {
+  Arg::with_name(name)
+    .long(long)
+    .takes_value(true)
+    .value_name("CAPACITY")
+    .default_value(DEFAULT_CACHE_CAPACITY)
+    .help(help)
+}
+
+fn storage_uri<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("storage_uri")
+    .short("m")
+    .long("storage")
+    .alias("mysql")
+    .takes_value(true)
+    .value_name("URI")
+    .required(true)
+    .help("Destination database URI (mysql://, postgres:// or sqlite://)")
+}
+
+fn database<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("database")
+    .short("d")
+    .long("database")
+    .takes_value(true)
+    .value_name("NAME")
+    .default_value(DEFAULT_DATABASE)
+    .help("Destination database name (ignored for sqlite://)")
+}
+
+fn table_prefix<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("table_prefix")
+    .short("p")
+    .long("table-prefix")
+    .takes_value(true)
+    .value_name("PREFIX")
+    .default_value("")
+    .help("Prefix prepended to all table names")
+}
+
+fn retry_interval<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("retry_interval")
+    .short("r")
+    .long("retry-interval")
+    .takes_value(true)
+    .value_name("INTERVAL")
+    .default_value(DEFAULT_RETRY_INTERVAL)
+    .help("Database connect retry interval in milliseconds")
+}
+
+fn calculation_limit<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("calculation_limit")
+    .short("t")
+    .long("calculation-limit")
+    .takes_value(true)
+    .value_name("LIMIT")
+    .default_value(DEFAULT_CALCULATION_LIMIT)
+    .help("Calculation depth limit")
+}
+
+fn batch_size<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("batch_size")
+    .long("batch-size")
+    .takes_value(true)
+    .value_name("SIZE")
+    .default_value(DEFAULT_BATCH_SIZE)
+    .help("Maximum number of records written by one update statement")
+}
+
+fn milestone_address<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("milestone_address")
+    .short("M")
+    .long("milestone-address")
+    .takes_value(true)
+    .value_name("ADDRESS")
+    .default_value(DEFAULT_MILESTONE_ADDRESS)
+    .help("Milestone address")
+}
+
+fn milestone_start_index<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("milestone_start_index")
+    .short("I")
+    .long("milestone-start-index")
+    .takes_value(true)
+    .value_name("INDEX")
+    .default_value(DEFAULT_MILESTONE_START_INDEX)
+    .help("Milestone start index")
+}
+
+fn log_config<'a, 'b>() -> Arg<'a, 'b> {
+  Arg::with_name("log_config")
+    .short("C")
+    .long("log-config")
+    .takes_value(true)
+    .value_name("FILE")
+    .default_value(DEFAULT_LOG_CONFIG)
+    .help("Path to log4rs configuration file")
 }
@@ -4,12 +4,24 @@ use std::{error, fmt, num, result};
 pub enum Error {
   ArgNotFound,
   RetryIntervalParseInt(num::ParseIntError),
+  ZmqTimeoutParseInt(num::ParseIntError),
   UpdateIntervalParseInt(num::ParseIntError),
   CalculationThreadsParseInt(num::ParseIntError),
   CalculationLimitParseInt(num::ParseIntError),
   GenerationLimitParseInt(num::ParseIntError),
+  FlushThresholdParseInt(num::ParseIntError),
+  FlushAgeParseInt(num::ParseIntError),
+  BatchSizeParseInt(num::ParseIntError),
+  WarmUpParseInt(num::ParseIntError),
   MilestoneStartIndexParseInt(num::ParseIntError),
   MilestoneStartIndexToTrits,
+  CacheCapacityParseInt(&'static str, num::ParseIntError),
+  QueueCapacityParseInt(&'static str, num::ParseIntError),
+  UnknownOverflow(String),
+  RestartLimitParseInt(num::ParseIntError),
+  RestartWindowParseInt(num::ParseIntError),
+  ReadyIdleParseInt(num::ParseIntError),
+  ReadyQueueParseInt(num::ParseIntError),
 }
 
 pub type Result<T> = result::Result<T, Error>;
@@ -21,6 +33,7 @@ impl fmt::Display for Error {
       Error::RetryIntervalParseInt(ref err) => {
         write!(f, "{} (retry-interval)", err)
       }
+      Error::ZmqTimeoutParseInt(ref err) => write!(f, "{} (zmq-timeout)", err),
       Error::UpdateIntervalParseInt(ref err) => {
         write!(f, "{} (update-interval)", err)
       }
@@ -33,12 +46,33 @@ impl fmt::Display for Error {
       Error::GenerationLimitParseInt(ref err) => {
         write!(f, "{} (generation-limit)", err)
       }
+      Error::FlushThresholdParseInt(ref err) => {
+        write!(f, "{} (flush-threshold)", err)
+      }
+      Error::FlushAgeParseInt(ref err) => write!(f, "{} (flush-age)", err),
+      Error::BatchSizeParseInt(ref err) => write!(f, "{} (batch-size)", err),
+      Error::WarmUpParseInt(ref err) => write!(f, "{} (warm-up)", err),
       Error::MilestoneStartIndexParseInt(ref err) => {
         write!(f, "{} (milestone-start-index)", err)
       }
       Error::MilestoneStartIndexToTrits => {
         write!(f, "can't convert to trits (milestone-start-index)")
       }
+      Error::CacheCapacityParseInt(name, ref err) |
+      Error::QueueCapacityParseInt(name, ref err) => {
+        write!(f, "{} ({})", err, name.replace('_', "-"))
+      }
+      Error::UnknownOverflow(ref policy) => {
+        write!(f, "unknown overflow policy `{}`", policy)
+      }
+      Error::RestartLimitParseInt(ref err) => {
+        write!(f, "{} (restart-limit)", err)
+      }
+      Error::RestartWindowParseInt(ref err) => {
+        write!(f, "{} (restart-window)", err)
+      }
+      Error::ReadyIdleParseInt(ref err) => write!(f, "{} (ready-idle)", err),
+      Error::ReadyQueueParseInt(ref err) => write!(f, "{} (ready-queue)", err),
     }
   }
 }
@@ -48,24 +82,49 @@ impl error::Error for Error {
     match *self {
       Error::ArgNotFound => "Argument not found",
       Error::RetryIntervalParseInt(ref err) |
+      Error::ZmqTimeoutParseInt(ref err) |
       Error::UpdateIntervalParseInt(ref err) |
       Error::CalculationThreadsParseInt(ref err) |
       Error::CalculationLimitParseInt(ref err) |
       Error::GenerationLimitParseInt(ref err) |
-      Error::MilestoneStartIndexParseInt(ref err) => err.description(),
+      Error::FlushThresholdParseInt(ref err) |
+      Error::FlushAgeParseInt(ref err) |
+      Error::BatchSizeParseInt(ref err) |
+      Error::WarmUpParseInt(ref err) |
+      Error::MilestoneStartIndexParseInt(ref err) |
+      Error::CacheCapacityParseInt(_, ref err) |
+      Error::QueueCapacityParseInt(_, ref err) |
+      Error::RestartLimitParseInt(ref err) |
+      Error::RestartWindowParseInt(ref err) |
+      Error::ReadyIdleParseInt(ref err) |
+      Error::ReadyQueueParseInt(ref err) => err.description(),
       Error::MilestoneStartIndexToTrits => "Can't convert to trits",
+      Error::UnknownOverflow(_) => "Unknown overflow policy",
     }
   }
 
   fn cause(&self) -> Option<&error::Error> {
     match *self {
-      Error::ArgNotFound | Error::MilestoneStartIndexToTrits => None,
+      Error::ArgNotFound |
+      Error::MilestoneStartIndexToTrits |
+      Error::UnknownOverflow(_) => None,
       Error::RetryIntervalParseInt(ref err) |
+      Error::ZmqTimeoutParseInt(ref err) |
       Error::UpdateIntervalParseInt(ref err) |
       Error::CalculationThreadsParseInt(ref err) |
       Error::CalculationLimitParseInt(ref err) |
       Error::GenerationLimitParseInt(ref err) |
-      Error::MilestoneStartIndexParseInt(ref err) => Some(err),
+      Error::FlushThresholdParseInt(ref err) |
+      Error::FlushAgeParseInt(ref err) |
+      Error::BatchSizeParseInt(ref err) |
+      Error::WarmUpParseInt(ref err) |
+      Error::MilestoneStartIndexParseInt(ref err) |
+      Error::CacheCapacityParseInt(_, ref err) |
+      Error::QueueCapacityParseInt(_, ref err) |
+      Error::RestartLimitParseInt(ref err) |
+      Error::RestartWindowParseInt(ref err) |
+      Error::ReadyIdleParseInt(ref err) |
+      Error::ReadyQueueParseInt(ref err) => Some(err),
     }
   }
 }
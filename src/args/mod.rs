@@ -2,75 +2,304 @@ mod error;
 
 pub use self::error::{Error, Result};
 use clap::ArgMatches;
-use message::transaction_message::TAG_LENGTH;
-use utils;
+use iota_rdb::message::transaction_message::TAG_LENGTH;
+use iota_rdb::utils;
+use iota_rdb::worker::Overflow;
+
+pub enum Source<'a> {
+  Zmq(Vec<&'a str>),
+  Replay(&'a str, bool),
+}
 
 pub struct Args<'a> {
-  pub zmq_uri: &'a str,
-  pub mysql_uri: &'a str,
+  pub source: Source<'a>,
+  pub trytes: bool,
+  pub zmq_timeout: u64,
+  pub record: Option<&'a str>,
+  pub storage_uri: &'a str,
+  pub database: &'a str,
+  pub table_prefix: &'a str,
   pub retry_interval: u64,
+  pub migrate: bool,
   pub update_interval: u64,
+  pub flush_threshold: usize,
+  pub flush_age: u64,
   pub calculation_threads: usize,
   pub calculation_limit: usize,
   pub generation_limit: usize,
+  pub batch_size: usize,
+  pub warm_up_limit: usize,
+  pub transaction_cache: usize,
+  pub address_cache: usize,
+  pub bundle_cache: usize,
+  pub insert_queue: usize,
+  pub node_queue: usize,
+  pub approve_queue: usize,
+  pub solidate_queue: usize,
+  pub calculate_queue: usize,
+  pub calculate_overflow: Overflow,
+  pub restart_limit: usize,
+  pub restart_window: u64,
+  pub http_address: Option<&'a str>,
+  pub ready_idle: u64,
+  pub ready_queue: usize,
+  pub milestone_address: &'a str,
+  pub milestone_start_index: String,
+  pub log_config: &'a str,
+}
+
+pub struct RetryArgs<'a> {
+  pub storage_uri: &'a str,
+  pub database: &'a str,
+  pub table_prefix: &'a str,
+  pub retry_interval: u64,
+  pub calculation_limit: usize,
+  pub batch_size: usize,
   pub milestone_address: &'a str,
   pub milestone_start_index: String,
   pub log_config: &'a str,
 }
 
+pub struct MigrateArgs<'a> {
+  pub storage_uri: &'a str,
+  pub database: &'a str,
+  pub table_prefix: &'a str,
+  pub retry_interval: u64,
+  pub log_config: &'a str,
+}
+
 impl<'a> Args<'a> {
   pub fn parse(matches: &'a ArgMatches<'a>) -> Result<Self> {
-    let zmq_uri = matches.value_of("zmq_uri").ok_or(Error::ArgNotFound)?;
-    let mysql_uri = matches.value_of("mysql_uri").ok_or(Error::ArgNotFound)?;
-    let retry_interval = matches
-      .value_of("retry_interval")
-      .ok_or(Error::ArgNotFound)?
-      .parse()
-      .map_err(Error::RetryIntervalParseInt)?;
+    let source = match matches.value_of("replay_file") {
+      Some(file) => Source::Replay(file, matches.is_present("paced")),
+      None => {
+        let zmq_uris = matches.values_of("zmq_uri").ok_or(Error::ArgNotFound)?;
+        Source::Zmq(zmq_uris.collect())
+      }
+    };
+    let trytes = matches.is_present("trytes");
+    let zmq_timeout = match matches.value_of("zmq_timeout") {
+      Some(zmq_timeout) => {
+        zmq_timeout.parse().map_err(Error::ZmqTimeoutParseInt)?
+      }
+      None => 0,
+    };
+    let record = matches.value_of("record");
+    let storage_uri =
+      matches.value_of("storage_uri").ok_or(Error::ArgNotFound)?;
+    let database = matches.value_of("database").ok_or(Error::ArgNotFound)?;
+    let table_prefix =
+      matches.value_of("table_prefix").ok_or(Error::ArgNotFound)?;
+    let retry_interval = retry_interval(matches)?;
+    let migrate = matches.is_present("migrate");
     let update_interval = matches
       .value_of("update_interval")
       .ok_or(Error::ArgNotFound)?
       .parse()
       .map_err(Error::UpdateIntervalParseInt)?;
+    let flush_threshold = matches
+      .value_of("flush_threshold")
+      .ok_or(Error::ArgNotFound)?
+      .parse()
+      .map_err(Error::FlushThresholdParseInt)?;
+    let flush_age = matches
+      .value_of("flush_age")
+      .ok_or(Error::ArgNotFound)?
+      .parse()
+      .map_err(Error::FlushAgeParseInt)?;
     let calculation_threads = matches
       .value_of("calculation_threads")
       .ok_or(Error::ArgNotFound)?
       .parse()
       .map_err(Error::CalculationThreadsParseInt)?;
-    let calculation_limit = matches
-      .value_of("calculation_limit")
-      .ok_or(Error::ArgNotFound)?
-      .parse()
-      .map_err(Error::CalculationLimitParseInt)?;
+    let calculation_limit = calculation_limit(matches)?;
     let generation_limit = matches
       .value_of("generation_limit")
       .ok_or(Error::ArgNotFound)?
       .parse()
       .map_err(Error::GenerationLimitParseInt)?;
+    let batch_size = batch_size(matches)?;
+    let warm_up_limit = matches
+      .value_of("warm_up")
+      .ok_or(Error::ArgNotFound)?
+      .parse()
+      .map_err(Error::WarmUpParseInt)?;
+    let transaction_cache = cache_capacity(matches, "transaction_cache")?;
+    let address_cache = cache_capacity(matches, "address_cache")?;
+    let bundle_cache = cache_capacity(matches, "bundle_cache")?;
+    let insert_queue = queue_capacity(matches, "insert_queue")?;
+    let node_queue = queue_capacity(matches, "node_queue")?;
+    let approve_queue = queue_capacity(matches, "approve_queue")?;
+    let solidate_queue = queue_capacity(matches, "solidate_queue")?;
+    let calculate_queue = queue_capacity(matches, "calculate_queue")?;
+    let calculate_overflow = matches
+      .value_of("calculate_overflow")
+      .ok_or(Error::ArgNotFound)?;
+    let calculate_overflow = Overflow::parse(calculate_overflow)
+      .ok_or_else(|| Error::UnknownOverflow(calculate_overflow.to_owned()))?;
+    let restart_limit = matches
+      .value_of("restart_limit")
+      .ok_or(Error::ArgNotFound)?
+      .parse()
+      .map_err(Error::RestartLimitParseInt)?;
+    let restart_window = matches
+      .value_of("restart_window")
+      .ok_or(Error::ArgNotFound)?
+      .parse()
+      .map_err(Error::RestartWindowParseInt)?;
+    let http_address = matches.value_of("http");
+    let ready_idle = matches
+      .value_of("ready_idle")
+      .ok_or(Error::ArgNotFound)?
+      .parse()
+      .map_err(Error::ReadyIdleParseInt)?;
+    let ready_queue = matches
+      .value_of("ready_queue")
+      .ok_or(Error::ArgNotFound)?
+      .parse()
+      .map_err(Error::ReadyQueueParseInt)?;
     let milestone_address = matches
       .value_of("milestone_address")
       .ok_or(Error::ArgNotFound)?;
-    let milestone_start_index = utils::trits_string(
-      matches
-        .value_of("milestone_start_index")
-        .ok_or(Error::ArgNotFound)?
-        .parse()
-        .map_err(Error::MilestoneStartIndexParseInt)?,
-      TAG_LENGTH,
-    ).ok_or(Error::MilestoneStartIndexToTrits)?;
+    let milestone_start_index = milestone_start_index(matches)?;
     let log_config = matches.value_of("log_config").ok_or(Error::ArgNotFound)?;
 
     Ok(Self {
-      zmq_uri,
-      mysql_uri,
+      source,
+      trytes,
+      zmq_timeout,
+      record,
+      storage_uri,
+      database,
+      table_prefix,
       retry_interval,
+      migrate,
       update_interval,
+      flush_threshold,
+      flush_age,
       calculation_threads,
       calculation_limit,
       generation_limit,
+      batch_size,
+      warm_up_limit,
+      transaction_cache,
+      address_cache,
+      bundle_cache,
+      insert_queue,
+      node_queue,
+      approve_queue,
+      solidate_queue,
+      calculate_queue,
+      calculate_overflow,
+      restart_limit,
+      restart_window,
+      http_address,
+      ready_idle,
+      ready_queue,
+      milestone_address,
+      milestone_start_index,
+      log_config,
+    })
+  }
+}
+
+impl<'a> MigrateArgs<'a> {
+  pub fn parse(matches: &'a ArgMatches<'a>) -> Result<Self> {
+    let storage_uri =
+      matches.value_of("storage_uri").ok_or(Error::ArgNotFound)?;
+    let database = matches.value_of("database").ok_or(Error::ArgNotFound)?;
+    let table_prefix =
+      matches.value_of("table_prefix").ok_or(Error::ArgNotFound)?;
+    let retry_interval = retry_interval(matches)?;
+    let log_config = matches.value_of("log_config").ok_or(Error::ArgNotFound)?;
+
+    Ok(Self {
+      storage_uri,
+      database,
+      table_prefix,
+      retry_interval,
+      log_config,
+    })
+  }
+}
+
+impl<'a> RetryArgs<'a> {
+  pub fn parse(matches: &'a ArgMatches<'a>) -> Result<Self> {
+    let storage_uri =
+      matches.value_of("storage_uri").ok_or(Error::ArgNotFound)?;
+    let database = matches.value_of("database").ok_or(Error::ArgNotFound)?;
+    let table_prefix =
+      matches.value_of("table_prefix").ok_or(Error::ArgNotFound)?;
+    let retry_interval = retry_interval(matches)?;
+    let calculation_limit = calculation_limit(matches)?;
+    let batch_size = batch_size(matches)?;
+    let milestone_address = matches
+      .value_of("milestone_address")
+      .ok_or(Error::ArgNotFound)?;
+    let milestone_start_index = milestone_start_index(matches)?;
+    let log_config = matches.value_of("log_config").ok_or(Error::ArgNotFound)?;
+
+    Ok(Self {
+      storage_uri,
+      database,
+      table_prefix,
+      retry_interval,
+      calculation_limit,
+      batch_size,
       milestone_address,
       milestone_start_index,
       log_config,
     })
   }
 }
+
+fn retry_interval(matches: &ArgMatches) -> Result<u64> {
+  matches
+    .value_of("retry_interval")
+    .ok_or(Error::ArgNotFound)?
+    .parse()
+    .map_err(Error::RetryIntervalParseInt)
+}
+
+fn calculation_limit(matches: &ArgMatches) -> Result<usize> {
+  matches
+    .value_of("calculation_limit")
+    .ok_or(Error::ArgNotFound)?
+    .parse()
+    .map_err(Error::CalculationLimitParseInt)
+}
+
+fn batch_size(matches: &ArgMatches) -> Result<usize> {
+  matches
+    .value_of("batch_size")
+    .ok_or(Error::ArgNotFound)?
+    .parse()
+    .map_err(Error::BatchSizeParseInt)
+}
+
+fn cache_capacity(matches: &ArgMatches, name: &'static str) -> Result<usize> {
+  matches
+    .value_of(name)
+    .ok_or(Error::ArgNotFound)?
+    .parse()
+    .map_err(|err| Error::CacheCapacityParseInt(name, err))
+}
+
+fn queue_capacity(matches: &ArgMatches, name: &'static str) -> Result<usize> {
+  matches
+    .value_of(name)
+    .ok_or(Error::ArgNotFound)?
+    .parse()
+    .map_err(|err| Error::QueueCapacityParseInt(name, err))
+}
+
+fn milestone_start_index(matches: &ArgMatches) -> Result<String> {
+  utils::trits_string(
+    matches
+      .value_of("milestone_start_index")
+      .ok_or(Error::ArgNotFound)?
+      .parse()
+      .map_err(Error::MilestoneStartIndexParseInt)?,
+    TAG_LENGTH,
+  ).ok_or(Error::MilestoneStartIndexToTrits)
+}
@@ -0,0 +1,93 @@
+use iota_trytes;
+
+pub const HASH_LENGTH: usize = 243;
+const STATE_LENGTH: usize = HASH_LENGTH * 3;
+const NUMBER_OF_ROUNDS: usize = 81;
+const TRUTH_TABLE: [i8; 11] = [1, 0, -1, 2, 1, -1, 0, 2, -1, 1, 0];
+
+pub struct Curl {
+  state: Vec<i8>,
+  scratchpad: Vec<i8>,
+}
+
+impl Default for Curl {
+  fn default() -> Self {
+    Self {
+      state: vec![0; STATE_LENGTH],
+      scratchpad: vec![0; STATE_LENGTH],
+    }
+  }
+}
+
+impl Curl {
+  pub fn absorb(&mut self, trits: &[i8]) {
+    for chunk in trits.chunks(HASH_LENGTH) {
+      self.state[..chunk.len()].copy_from_slice(chunk);
+      self.transform();
+    }
+  }
+
+  pub fn squeeze(&mut self, trits: &mut [i8]) {
+    for chunk in trits.chunks_mut(HASH_LENGTH) {
+      let len = chunk.len();
+      chunk.copy_from_slice(&self.state[..len]);
+      self.transform();
+    }
+  }
+
+  fn transform(&mut self) {
+    let mut index = 0;
+    for _ in 0..NUMBER_OF_ROUNDS {
+      self.scratchpad.copy_from_slice(&self.state);
+      for i in 0..STATE_LENGTH {
+        let prev = index;
+        index = if index < 365 { index + 364 } else { index - 365 };
+        let trit = self.scratchpad[prev] + (self.scratchpad[index] << 2) + 5;
+        self.state[i] = TRUTH_TABLE[trit as usize];
+      }
+    }
+  }
+}
+
+pub fn transaction_hash(trits: &[i8]) -> Option<String> {
+  let mut hash = [0; HASH_LENGTH];
+  let mut curl = Curl::default();
+  curl.absorb(trits);
+  curl.squeeze(&mut hash);
+  iota_trytes::trits_to_string(&hash)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const TRYTES: &str = "EMIDYNHBWMBCXVDEFOFWINXTERALUKYYPPHKP9JJFG\
+                        JEIUY9MUDVNFZHMMWZUYUSWAIOWEVTHNWMHANBH";
+
+  fn trits(trytes: &str) -> Vec<i8> {
+    trytes
+      .chars()
+      .flat_map(iota_trytes::char_to_trits)
+      .cloned()
+      .collect()
+  }
+
+  #[test]
+  fn hashes_known_vector() {
+    assert_eq!(
+      transaction_hash(&trits(TRYTES)).unwrap(),
+      "AQBOPUMJMGVHFOXSMUAGZNACKUTISDPBSILMRAGIGRX\
+       XS9JJTLIKZUW9BCJWKSTFBDSBLNVEEGVGAMSSM"
+    );
+  }
+
+  #[test]
+  fn hashes_transaction_length_input() {
+    let trytes = TRYTES.repeat(33);
+    assert_eq!(
+      transaction_hash(&trits(&trytes)).unwrap(),
+      "YENVIRMYVUZISSYURQKVUNWXPJWTOBLNQSVFJNMYAEC\
+       CGCHZHHV9GYOTUNPGUDCHBZFOSTJEEK9AFGXOF"
+    );
+  }
+}
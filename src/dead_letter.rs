@@ -0,0 +1,103 @@
+use storage::{Connection, Result, Row, Table};
+
+const TABLE: &Table = &Table {
+  name: "failed_messages",
+  id: "id_failed_message",
+  hash: "id_failed_message",
+  columns: &["id_failed_message", "message", "stage", "error", "timestamp"],
+  update_columns: &["stage", "error", "timestamp"],
+};
+
+#[derive(Clone, Copy, Debug)]
+pub enum Stage {
+  Parse,
+  Process,
+}
+
+#[derive(Debug)]
+pub struct DeadLetter {
+  pub id: u64,
+  pub message: String,
+  pub stage: String,
+  pub error: String,
+  pub timestamp: f64,
+}
+
+impl Stage {
+  pub fn as_str(&self) -> &'static str {
+    match *self {
+      Stage::Parse => "parse",
+      Stage::Process => "process",
+    }
+  }
+}
+
+impl DeadLetter {
+  pub fn find_all(conn: &mut Connection) -> Result<Vec<Self>> {
+    let mut dead_letters = Vec::new();
+    for mut row in conn.find_all(TABLE)? {
+      dead_letters.push(Self {
+        id: row.take("id_failed_message")?,
+        message: row.take("message")?,
+        stage: row.take("stage")?,
+        error: row.take("error")?,
+        timestamp: row.take("timestamp")?,
+      });
+    }
+    Ok(dead_letters)
+  }
+
+  pub fn update(
+    &self,
+    conn: &mut Connection,
+    stage: Stage,
+    error: &str,
+    timestamp: f64,
+  ) -> Result<()> {
+    conn.update(
+      TABLE,
+      &row!{
+        "id_failed_message" => self.id,
+        "stage" => stage.as_str(),
+        "error" => error,
+        "timestamp" => timestamp,
+      },
+    )
+  }
+}
+
+pub fn delete_all(conn: &mut Connection, ids: &[u64]) -> Result<()> {
+  conn.begin()?;
+  let result = delete_ids(conn, ids).and_then(|()| conn.commit());
+  if result.is_err() {
+    if let Err(err) = conn.rollback() {
+      error!("Rollback failure: {}", err);
+    }
+  }
+  result
+}
+
+pub fn insert(
+  conn: &mut Connection,
+  message: &str,
+  stage: Stage,
+  error: &str,
+  timestamp: f64,
+) -> Result<()> {
+  conn.insert(
+    TABLE,
+    &row!{
+      "message" => message,
+      "stage" => stage.as_str(),
+      "error" => error,
+      "timestamp" => timestamp,
+    },
+  )
+}
+
+fn delete_ids(conn: &mut Connection, ids: &[u64]) -> Result<()> {
+  for &id in ids {
+    conn.delete(TABLE, id)?;
+  }
+  Ok(())
+}
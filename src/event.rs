@@ -1,81 +1,47 @@
-use mysql::{self, QueryResult, Result};
-
-const QUERY: &str = r#"
-  INSERT INTO txload (
-    event, count, timestamp
-  ) VALUES (
-    :event, :count, :timestamp
-  )
-"#;
+use storage::{Connection, Result};
 
 pub fn new_transaction_received(
-  conn: &mut mysql::Conn,
+  conn: &mut Connection,
   timestamp: f64,
-) -> Result<QueryResult> {
-  conn.prep_exec(
-    QUERY,
-    params!{
-      "event" => "NTX",
-      "count" => 1,
-      "timestamp" => timestamp,
-    },
-  )
+) -> Result<()> {
+  conn.insert_event("NTX", 1, timestamp)
 }
 
-pub fn milestone_received(
-  conn: &mut mysql::Conn,
-  timestamp: f64,
-) -> Result<QueryResult> {
-  conn.prep_exec(
-    QUERY,
-    params!{
-      "event" => "MST",
-      "count" => 1,
-      "timestamp" => timestamp,
-    },
-  )
+pub fn milestone_received(conn: &mut Connection, timestamp: f64) -> Result<()> {
+  conn.insert_event("MST", 1, timestamp)
 }
 
 pub fn subtangle_confirmation(
-  conn: &mut mysql::Conn,
+  conn: &mut Connection,
   timestamp: f64,
   count: i32,
-) -> Result<QueryResult> {
-  conn.prep_exec(
-    QUERY,
-    params!{
-      "event" => "CNF",
-      "count" => count,
-      "timestamp" => timestamp,
-    },
-  )
+) -> Result<()> {
+  conn.insert_event("CNF", count, timestamp)
 }
 
 pub fn unsolid_transaction(
-  conn: &mut mysql::Conn,
+  conn: &mut Connection,
   timestamp: f64,
-) -> Result<QueryResult> {
-  conn.prep_exec(
-    QUERY,
-    params!{
-      "event" => "UNS",
-      "count" => 1,
-      "timestamp" => timestamp,
-    },
-  )
+) -> Result<()> {
+  conn.insert_event("UNS", 1, timestamp)
 }
 
 pub fn subtangle_solidation(
-  conn: &mut mysql::Conn,
+  conn: &mut Connection,
   timestamp: f64,
   count: i32,
-) -> Result<QueryResult> {
-  conn.prep_exec(
-    QUERY,
-    params!{
-      "event" => "SOL",
-      "count" => count,
-      "timestamp" => timestamp,
-    },
-  )
+) -> Result<()> {
+  conn.insert_event("SOL", count, timestamp)
+}
+
+pub fn zmq_reconnect(conn: &mut Connection, timestamp: f64) -> Result<()> {
+  conn.insert_event("ZMQ", 1, timestamp)
+}
+
+pub fn counts(conn: &mut Connection, since: f64) -> Result<Vec<(String, i64)>> {
+  conn
+    .event_counts(since)?
+    .into_iter()
+    .map(|mut row| Ok((row.take("event")?, row.take_or("count", 0)?)))
+    .collect()
 }
@@ -0,0 +1,154 @@
+use std::mem;
+use std::sync::Mutex;
+use storage::{Connection, Result, Table};
+use worker::{Push, Queue};
+
+const TABLE: &Table = &Table {
+  name: "job_queue",
+  id: "id_job",
+  hash: "id_job",
+  columns: &["id_job", "kind", "data"],
+  update_columns: &[],
+};
+
+pub trait Durable: Sized {
+  const KIND: &'static str;
+
+  fn encode(&self) -> String;
+
+  fn decode(data: &str) -> Option<Self>;
+}
+
+#[derive(Debug)]
+pub struct Pending<T> {
+  pub id: Option<u64>,
+  pub job: T,
+}
+
+#[derive(Debug)]
+pub struct StoredJob {
+  pub id: u64,
+  pub kind: String,
+  pub data: String,
+}
+
+pub struct JobStore {
+  counter: Mutex<u64>,
+  acked: Mutex<Vec<u64>>,
+}
+
+impl JobStore {
+  pub fn new(conn: &mut Connection) -> Result<Self> {
+    let counter = conn.last_id(TABLE)?.unwrap_or(0);
+    Ok(Self {
+      counter: Mutex::new(counter),
+      acked: Mutex::new(Vec::new()),
+    })
+  }
+
+  pub fn ack<T>(&self, pending: &Pending<T>) {
+    if let Some(id) = pending.id {
+      debug!("Mutex lock");
+      let mut acked = self.acked.lock().unwrap();
+      debug!("Mutex acquire");
+      acked.push(id);
+    }
+  }
+
+  pub fn take_acked(&self) -> Vec<u64> {
+    debug!("Mutex lock");
+    let mut acked = self.acked.lock().unwrap();
+    debug!("Mutex acquire");
+    mem::replace(&mut *acked, Vec::new())
+  }
+
+  pub fn restore(&self, ids: Vec<u64>) {
+    debug!("Mutex lock");
+    let mut acked = self.acked.lock().unwrap();
+    debug!("Mutex acquire");
+    acked.extend(ids);
+  }
+
+  pub fn insert<T: Durable>(
+    &self,
+    conn: &mut Connection,
+    job: &T,
+  ) -> Result<u64> {
+    let id = {
+      debug!("Mutex lock");
+      let mut counter = self.counter.lock().unwrap();
+      debug!("Mutex acquire");
+      *counter += 1;
+      *counter
+    };
+    conn.insert(
+      TABLE,
+      &row!{
+        "id_job" => id,
+        "kind" => T::KIND,
+        "data" => job.encode(),
+      },
+    )?;
+    Ok(id)
+  }
+}
+
+impl StoredJob {
+  pub fn decode<T: Durable>(&self) -> Option<Pending<T>> {
+    if self.kind != T::KIND {
+      return None;
+    }
+    match T::decode(&self.data) {
+      Some(job) => Some(Pending {
+        id: Some(self.id),
+        job,
+      }),
+      None => {
+        warn!("Malformed {} job #{}: {}", self.kind, self.id, self.data);
+        None
+      }
+    }
+  }
+}
+
+impl<T: PartialEq> PartialEq for Pending<T> {
+  fn eq(&self, other: &Self) -> bool {
+    self.job == other.job
+  }
+}
+
+pub fn delete(conn: &mut Connection, id: u64) -> Result<()> {
+  conn.delete(TABLE, id)
+}
+
+pub fn enqueue<T: PartialEq>(
+  conn: &mut Connection,
+  queue: &Queue<Pending<T>>,
+  pending: Pending<T>,
+) -> Push {
+  let id = pending.id;
+  let push = queue.push(pending);
+  match push {
+    Push::Coalesced | Push::Dropped => {
+      if let Some(id) = id {
+        if let Err(err) = delete(conn, id) {
+          error!("Job ack failure: {}", err);
+        }
+      }
+    }
+    Push::Queued | Push::Closed => {}
+  }
+  push
+}
+
+pub fn find_all(conn: &mut Connection) -> Result<Vec<StoredJob>> {
+  let mut jobs = Vec::new();
+  for mut row in conn.find_all(TABLE)? {
+    jobs.push(StoredJob {
+      id: row.take("id_job")?,
+      kind: row.take("kind")?,
+      data: row.take("data")?,
+    });
+  }
+  Ok(jobs)
+}
@@ -0,0 +1,38 @@
+#![cfg_attr(feature = "clippy", feature(plugin))]
+#![cfg_attr(feature = "clippy", plugin(clippy))]
+
+extern crate iota_kerl;
+extern crate iota_sign;
+extern crate iota_trytes;
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate mysql;
+#[macro_use]
+extern crate postgres;
+extern crate rusqlite;
+extern crate zmq;
+
+#[macro_use]
+mod macros;
+pub mod worker;
+pub mod message;
+pub mod mapper;
+pub mod solid;
+pub mod event;
+pub mod storage;
+pub mod utils;
+pub mod curl;
+pub mod dead_letter;
+pub mod job_queue;
+pub mod metrics;
+
+pub use mapper::{AddressMapper, BundleMapper, Mapper, TransactionMapper};
+pub use message::{NodeMessage, TransactionMessage};
+pub use metrics::{MeteredStorage, Metrics};
+pub use storage::{Connection, MysqlStorage, PostgresStorage, SqliteStorage,
+                  Storage};
+pub use worker::{ApproveThread, CalculateThreads, HttpThread, InsertThread,
+                 MonitorThread, NodeThread, Overflow, Queue, Recorder,
+                 ReplayLoop, SolidateThread, SourceStats, Supervisor,
+                 UpdateThread, ZmqLoop, ZmqSource};
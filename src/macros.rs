@@ -13,3 +13,11 @@ macro_rules! impl_getter {
     }
   };
 }
+
+macro_rules! row {
+  ($($column:expr => $value:expr),* $(,)*) => {{
+    let mut row = $crate::storage::Row::new();
+    $(row.push($column, $value);)*
+    row
+  }};
+}
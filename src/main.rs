@@ -1,139 +1,571 @@
 #![cfg_attr(feature = "clippy", feature(plugin))]
 #![cfg_attr(feature = "clippy", plugin(clippy))]
-#![feature(iterator_for_each)]
 
 #[macro_use]
 extern crate clap;
-extern crate iota_kerl;
-extern crate iota_sign;
-extern crate iota_trytes;
+extern crate ctrlc;
+extern crate iota_rdb;
 extern crate log4rs;
 #[macro_use]
 extern crate log;
-#[macro_use]
-extern crate mysql;
 extern crate zmq;
 
-#[macro_use]
-mod macros;
 mod app;
 mod args;
-mod worker;
-mod message;
-mod mapper;
-mod solid;
-mod event;
-mod utils;
-
-use args::Args;
-use mapper::{AddressMapper, BundleMapper, Mapper, TransactionMapper};
+
+use args::{Args, MigrateArgs, RetryArgs, Source};
+use clap::ArgMatches;
+use iota_rdb::{AddressMapper, ApproveThread, BundleMapper, CalculateThreads,
+               HttpThread, InsertThread, Mapper, MeteredStorage, Metrics,
+               MonitorThread, NodeThread, Overflow, Queue, Recorder,
+               ReplayLoop, SolidateThread, SourceStats, Supervisor,
+               TransactionMapper, UpdateThread, ZmqLoop, ZmqSource};
+use iota_rdb::dead_letter;
+use iota_rdb::job_queue::{self, Durable, JobStore, Pending, StoredJob};
+use iota_rdb::mapper::{self, CacheMonitor};
+use iota_rdb::worker::{ApproveJob, CalculateJob, DeadLetterRetry, Monitor,
+                       Push, SolidateJob};
+use iota_rdb::storage::{self, migration, Connection, Options, Storage};
+use std::fs::File;
+use std::io::BufReader;
 use std::process::exit;
 use std::sync::{mpsc, Arc};
-use utils::MysqlConnUtils;
-use worker::{ApproveThread, CalculateThreads, InsertThread, SolidateThread,
-             UpdateThread, ZmqLoop};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
 
 fn main() {
   let matches = app::build().get_matches();
-  let args = Args::parse(&matches).unwrap_or_else(|err| {
+  match matches.subcommand() {
+    ("migrate", Some(matches)) => migrate(matches),
+    ("retry-dead-letters", Some(matches)) => retry_dead_letters(matches),
+    ("replay", Some(matches)) => run(matches),
+    _ => run(&matches),
+  }
+}
+
+fn migrate(matches: &ArgMatches) {
+  let args = MigrateArgs::parse(matches).unwrap_or_else(|err| {
+    eprintln!("Invalid arguments: {}", err);
+    exit(1);
+  });
+  let MigrateArgs {
+    storage_uri,
+    database,
+    table_prefix,
+    retry_interval,
+    log_config,
+  } = args;
+  init_log(log_config);
+
+  let storage = open_storage(
+    storage_uri,
+    Options {
+      database: database.to_owned(),
+      prefix: table_prefix.to_owned(),
+      retry_interval,
+    },
+  );
+  let mut conn = storage.connect();
+  match migration::migrate(&mut *conn, storage.migrations()) {
+    Ok(version) => info!("Database schema version: {}", version),
+    Err(err) => {
+      error!("Migration failure: {}", err);
+      exit(1);
+    }
+  }
+}
+
+fn run(matches: &ArgMatches) {
+  let args = Args::parse(matches).unwrap_or_else(|err| {
     eprintln!("Invalid arguments: {}", err);
     exit(1);
   });
   let Args {
-    zmq_uri,
-    mysql_uri,
+    source,
+    trytes,
+    zmq_timeout,
+    record,
+    storage_uri,
+    database,
+    table_prefix,
     retry_interval,
+    migrate,
     update_interval,
+    flush_threshold,
+    flush_age,
     calculation_threads,
     calculation_limit,
     generation_limit,
+    batch_size,
+    warm_up_limit,
+    transaction_cache,
+    address_cache,
+    bundle_cache,
+    insert_queue,
+    node_queue,
+    approve_queue,
+    solidate_queue,
+    calculate_queue,
+    calculate_overflow,
+    restart_limit,
+    restart_window,
+    http_address,
+    ready_idle,
+    ready_queue,
     milestone_address,
     milestone_start_index,
     log_config,
   } = args;
-  log4rs::init_file(log_config, Default::default()).unwrap_or_else(|err| {
-    eprintln!("Error while processing logger configuration file: {}", err);
-    exit(1);
-  });
+  init_log(log_config);
 
-  let (insert_tx, insert_rx) = mpsc::channel();
-  let (approve_tx, approve_rx) = mpsc::channel();
-  let (solidate_tx, solidate_rx) = mpsc::channel();
-  let (calculate_tx, calculate_rx) = mpsc::channel();
-  let ctx = zmq::Context::new();
-  let socket = ctx.socket(zmq::SUB).expect("ZMQ socket create failure");
-  socket.connect(zmq_uri).expect("ZMQ socket connect failure");
-  socket.set_subscribe(b"tx ").expect("ZMQ subscribe failure");
+  let source_queue = Queue::new("source", insert_queue, Overflow::Block);
+  let insert_queue = Queue::new("insert", insert_queue, Overflow::Block);
+  let node_queue = Queue::new("node", node_queue, Overflow::Block);
+  let approve_queue = Queue::new("approve", approve_queue, Overflow::Block);
+  let solidate_queue = Queue::new("solidate", solidate_queue, Overflow::Block);
+  let calculate_queue =
+    Queue::new("calculate", calculate_queue, calculate_overflow);
 
-  let mut conn = mysql::Conn::new_retry(mysql_uri, retry_interval);
+  let metrics = Arc::new(Metrics::new());
+  let storage: Arc<Storage> = Arc::new(MeteredStorage::new(
+    open_storage(
+      storage_uri,
+      Options {
+        database: database.to_owned(),
+        prefix: table_prefix.to_owned(),
+        retry_interval,
+      },
+    ),
+    metrics.clone(),
+  ));
+  let mut conn = storage.connect();
+  check_schema(&*storage, &mut *conn, migrate);
   let transaction_mapper = Arc::new(
-    TransactionMapper::new(&mut conn, retry_interval)
+    TransactionMapper::new(&mut *conn, retry_interval, transaction_cache)
       .expect("Transaction mapper failure"),
   );
   let address_mapper = Arc::new(
-    AddressMapper::new(&mut conn, retry_interval)
+    AddressMapper::new(&mut *conn, retry_interval, address_cache)
       .expect("Address mapper failure"),
   );
   let bundle_mapper = Arc::new(
-    BundleMapper::new(&mut conn, retry_interval)
+    BundleMapper::new(&mut *conn, retry_interval, bundle_cache)
       .expect("Bundle mapper failure"),
   );
+  let job_store =
+    Arc::new(JobStore::new(&mut *conn).expect("Job store failure"));
 
   info!("Milestone address: {}", milestone_address);
   info!("Milestone start index string: {}", milestone_start_index);
   info!("Initial `id_tx`: {}", transaction_mapper.current_id());
   info!("Initial `id_address`: {}", address_mapper.current_id());
   info!("Initial `id_bundle`: {}", bundle_mapper.current_id());
+  info!("Calculate queue overflow: {}", calculate_overflow.as_str());
+  warm_up(
+    &mut *conn,
+    warm_up_limit,
+    &transaction_mapper,
+    &address_mapper,
+    &bundle_mapper,
+  ).unwrap_or_else(|err| {
+    error!("Warm-up failure: {}", err);
+    exit(1);
+  });
 
+  let queues = || -> Vec<Box<Monitor>> {
+    vec![
+      Box::new(source_queue.clone()),
+      Box::new(insert_queue.clone()),
+      Box::new(node_queue.clone()),
+      Box::new(approve_queue.clone()),
+      Box::new(solidate_queue.clone()),
+      Box::new(calculate_queue.clone()),
+    ]
+  };
+  let caches: Vec<Arc<CacheMonitor>> = vec![
+    transaction_mapper.clone(),
+    address_mapper.clone(),
+    bundle_mapper.clone(),
+  ];
+  let monitor_thread = MonitorThread {
+    queues: queues(),
+    caches: caches.clone(),
+  };
   let insert_thread = InsertThread {
-    insert_rx,
-    approve_tx,
-    solidate_tx,
-    calculate_tx,
-    mysql_uri,
-    retry_interval,
+    insert_queue: insert_queue.clone(),
+    approve_queue: approve_queue.clone(),
+    solidate_queue: solidate_queue.clone(),
+    calculate_queue: calculate_queue.clone(),
+    storage: storage.clone(),
+    job_store: job_store.clone(),
     transaction_mapper: transaction_mapper.clone(),
     address_mapper: address_mapper.clone(),
     bundle_mapper: bundle_mapper.clone(),
-    milestone_address,
+    metrics: metrics.clone(),
+    milestone_address: milestone_address.to_owned(),
     milestone_start_index,
   };
+  let update_stop = Arc::new(AtomicBool::new(false));
   let update_thread = UpdateThread {
-    mysql_uri,
-    retry_interval,
+    storage: storage.clone(),
+    job_store: job_store.clone(),
+    metrics: metrics.clone(),
     update_interval,
+    flush_threshold,
+    flush_age,
     generation_limit,
+    batch_size,
     transaction_mapper: transaction_mapper.clone(),
     address_mapper: address_mapper.clone(),
     bundle_mapper: bundle_mapper.clone(),
+    stop: update_stop.clone(),
   };
   let approve_thread = ApproveThread {
-    approve_rx,
-    mysql_uri,
-    retry_interval,
+    approve_queue: approve_queue.clone(),
+    storage: storage.clone(),
+    job_store: job_store.clone(),
+    metrics: metrics.clone(),
     transaction_mapper: transaction_mapper.clone(),
     bundle_mapper: bundle_mapper.clone(),
   };
   let solidate_thread = SolidateThread {
-    solidate_rx,
-    mysql_uri,
-    retry_interval,
+    solidate_queue: solidate_queue.clone(),
+    storage: storage.clone(),
+    job_store: job_store.clone(),
+    metrics: metrics.clone(),
     transaction_mapper: transaction_mapper.clone(),
   };
   let calculate_threads = CalculateThreads {
-    calculate_rx,
-    mysql_uri,
-    retry_interval,
+    calculate_queue: calculate_queue.clone(),
+    storage: storage.clone(),
+    job_store: job_store.clone(),
+    metrics: metrics.clone(),
     calculation_threads,
     calculation_limit,
     transaction_mapper: transaction_mapper.clone(),
   };
-  let zmq_loop = ZmqLoop { socket, insert_tx };
-
-  insert_thread.spawn();
-  update_thread.spawn();
-  approve_thread.spawn();
-  solidate_thread.spawn();
-  calculate_threads.spawn();
-  zmq_loop.run();
+  let node_thread = NodeThread {
+    node_queue: node_queue.clone(),
+    storage: storage.clone(),
+  };
+  let supervisor = {
+    let transaction_mapper = transaction_mapper.clone();
+    let address_mapper = address_mapper.clone();
+    let bundle_mapper = bundle_mapper.clone();
+    Supervisor::new(restart_limit, restart_window, move || {
+      let transactions = transaction_mapper.purge_poisoned();
+      let addresses = address_mapper.purge_poisoned();
+      let bundles = bundle_mapper.purge_poisoned();
+      if transactions + addresses + bundles > 0 {
+        warn!(
+          "Purged poisoned records transactions: {}, addresses: {}, \
+           bundles: {}",
+          transactions,
+          addresses,
+          bundles
+        );
+      }
+    })
+  };
+  let http_thread = http_address.map(|address| HttpThread {
+    address: address.to_owned(),
+    storage: storage.clone(),
+    supervisor: supervisor.clone(),
+    metrics: metrics.clone(),
+    queues: queues(),
+    caches,
+    transaction_mapper: transaction_mapper.clone(),
+    address_mapper: address_mapper.clone(),
+    bundle_mapper: bundle_mapper.clone(),
+    ready_idle,
+    ready_queue,
+  });
+
+  let (signal_tx, signal_rx) = mpsc::channel();
+  ctrlc::set_handler(move || {
+    signal_tx.send(()).ok();
+  }).expect("Signal handler failure");
+
+  let insert_thread = supervisor
+    .spawn("insert".into(), move || insert_thread.clone().spawn());
+  let update_thread = supervisor
+    .spawn("update".into(), move || update_thread.clone().spawn());
+  let approve_thread = supervisor
+    .spawn("approve".into(), move || approve_thread.clone().spawn());
+  let solidate_thread = supervisor
+    .spawn("solidate".into(), move || solidate_thread.clone().spawn());
+  let calculate_threads = (0..calculation_threads)
+    .map(|i| {
+      let calculate_threads = calculate_threads.clone();
+      supervisor.spawn(format!("calculate#{}", i), move || {
+        calculate_threads.spawn_thread(i)
+      })
+    })
+    .collect::<Vec<_>>();
+  let node_thread =
+    supervisor.spawn("node".into(), move || node_thread.clone().spawn());
+  supervisor.watch("monitor".into(), monitor_thread.spawn());
+  if let Some(http_thread) = http_thread {
+    supervisor.watch("http".into(), http_thread.spawn());
+  }
+  resume_jobs(&mut *conn, &approve_queue, &solidate_queue, &calculate_queue)
+    .unwrap_or_else(|err| {
+      error!("Job resume failure: {}", err);
+      exit(1);
+    });
+  match source {
+    Source::Zmq(zmq_uris) => {
+      let ctx = zmq::Context::new();
+      for (index, zmq_uri) in zmq_uris.iter().enumerate() {
+        info!("ZMQ source #{}: {}", index, zmq_uri);
+        let zmq_source = ZmqSource {
+          index,
+          uri: (*zmq_uri).to_owned(),
+          ctx: ctx.clone(),
+          trytes,
+          timeout: zmq_timeout,
+          storage: storage.clone(),
+          source_queue: source_queue.clone(),
+        };
+        supervisor.spawn(format!("zmq#{}", index), move || {
+          zmq_source.clone().spawn()
+        });
+      }
+      let recorder = record.map(|path| {
+        Recorder::open(path).unwrap_or_else(|err| {
+          eprintln!("Error while opening record file: {}", err);
+          exit(1);
+        })
+      });
+      let zmq_loop = ZmqLoop {
+        source_queue: source_queue.clone(),
+        sources: zmq_uris.iter().map(|uri| SourceStats::new(uri)).collect(),
+        insert_queue: insert_queue.clone(),
+        node_queue: node_queue.clone(),
+        metrics: metrics.clone(),
+        recorder,
+      };
+      supervisor.watch("zmq".into(), zmq_loop.spawn());
+    }
+    Source::Replay(path, paced) => {
+      let file = File::open(path).unwrap_or_else(|err| {
+        eprintln!("Error while opening replay file: {}", err);
+        exit(1);
+      });
+      let replay_loop = ReplayLoop {
+        reader: BufReader::new(file),
+        paced,
+        insert_queue: insert_queue.clone(),
+        node_queue: node_queue.clone(),
+        metrics: metrics.clone(),
+      };
+      supervisor.watch("replay".into(), replay_loop.spawn());
+    }
+  }
+
+  signal_rx.recv().expect("Signal handler failure");
+  info!("Shutting down, draining queues");
+  source_queue.close();
+  insert_queue.close();
+  node_queue.close();
+  join(insert_thread);
+  join(node_thread);
+  approve_queue.close();
+  solidate_queue.close();
+  calculate_queue.close();
+  join(approve_thread);
+  join(solidate_thread);
+  for thread in calculate_threads {
+    join(thread);
+  }
+  update_stop.store(true, Ordering::SeqCst);
+  join(update_thread);
+  let result = flush(
+    &mut *conn,
+    batch_size,
+    &job_store.take_acked(),
+    &transaction_mapper,
+    &address_mapper,
+    &bundle_mapper,
+  );
+  match result {
+    Ok(updated) => info!("Final update: {}", updated),
+    Err(err) => {
+      error!("Final update failure: {}", err);
+      exit(1);
+    }
+  }
+}
+
+fn resume_jobs(
+  conn: &mut Connection,
+  approve_queue: &Queue<Pending<ApproveJob>>,
+  solidate_queue: &Queue<Pending<SolidateJob>>,
+  calculate_queue: &Queue<Pending<CalculateJob>>,
+) -> storage::Result<()> {
+  let jobs = job_queue::find_all(conn)?;
+  let approve = resume(conn, &jobs, approve_queue);
+  let solidate = resume(conn, &jobs, solidate_queue);
+  let calculate = resume(conn, &jobs, calculate_queue);
+  info!(
+    "Resumed jobs approve: {}, solidate: {}, calculate: {}",
+    approve,
+    solidate,
+    calculate
+  );
+  Ok(())
+}
+
+fn resume<T: Durable + PartialEq>(
+  conn: &mut Connection,
+  jobs: &[StoredJob],
+  queue: &Queue<Pending<T>>,
+) -> usize {
+  let mut count = 0;
+  for job in jobs.iter().filter_map(|job| job.decode::<T>()) {
+    if job_queue::enqueue(conn, queue, job) == Push::Queued {
+      count += 1;
+    }
+  }
+  count
+}
+
+fn warm_up(
+  conn: &mut Connection,
+  limit: usize,
+  transaction_mapper: &TransactionMapper,
+  address_mapper: &AddressMapper,
+  bundle_mapper: &BundleMapper,
+) -> mapper::Result<()> {
+  if limit == 0 {
+    return Ok(());
+  }
+  let (transactions, addresses, bundles) =
+    transaction_mapper.warm_up(conn, limit)?;
+  let addresses = address_mapper.preload(conn, &addresses)?;
+  let bundles = bundle_mapper.preload(conn, &bundles)?;
+  info!(
+    "Warm-up transactions: {}, addresses: {}, bundles: {}",
+    transactions,
+    addresses,
+    bundles
+  );
+  Ok(())
+}
+
+fn flush(
+  conn: &mut Connection,
+  batch_size: usize,
+  jobs: &[u64],
+  transaction_mapper: &TransactionMapper,
+  address_mapper: &AddressMapper,
+  bundle_mapper: &BundleMapper,
+) -> mapper::Result<usize> {
+  let mut updated = transaction_mapper.update(conn, batch_size, jobs)?;
+  updated += address_mapper.update(conn, batch_size, &[])?;
+  updated += bundle_mapper.update(conn, batch_size, &[])?;
+  Ok(updated)
+}
+
+fn join(thread: JoinHandle<()>) {
+  let name = thread.thread().name().unwrap_or("").to_owned();
+  if thread.join().is_err() {
+    error!("Thread `{}` panicked", name);
+  }
+}
+
+fn retry_dead_letters(matches: &ArgMatches) {
+  let args = RetryArgs::parse(matches).unwrap_or_else(|err| {
+    eprintln!("Invalid arguments: {}", err);
+    exit(1);
+  });
+  let RetryArgs {
+    storage_uri,
+    database,
+    table_prefix,
+    retry_interval,
+    calculation_limit,
+    batch_size,
+    milestone_address,
+    milestone_start_index,
+    log_config,
+  } = args;
+  init_log(log_config);
+
+  let storage = open_storage(
+    storage_uri,
+    Options {
+      database: database.to_owned(),
+      prefix: table_prefix.to_owned(),
+      retry_interval,
+    },
+  );
+  let mut conn = storage.connect();
+  check_schema(&*storage, &mut *conn, false);
+  let transaction_mapper =
+    TransactionMapper::new(&mut *conn, retry_interval, 0)
+      .expect("Transaction mapper failure");
+  let address_mapper = AddressMapper::new(&mut *conn, retry_interval, 0)
+    .expect("Address mapper failure");
+  let bundle_mapper = BundleMapper::new(&mut *conn, retry_interval, 0)
+    .expect("Bundle mapper failure");
+  let dead_letter_retry = DeadLetterRetry {
+    transaction_mapper: &transaction_mapper,
+    address_mapper: &address_mapper,
+    bundle_mapper: &bundle_mapper,
+    milestone_address,
+    milestone_start_index: &milestone_start_index,
+    calculation_limit,
+  };
+  let result = dead_letter_retry.perform(&mut *conn).and_then(|counts| {
+    let (retried, failed) = counts;
+    flush(
+      &mut *conn,
+      batch_size,
+      &[],
+      &transaction_mapper,
+      &address_mapper,
+      &bundle_mapper,
+    )?;
+    dead_letter::delete_all(&mut *conn, &retried)?;
+    Ok((retried.len(), failed))
+  });
+  match result {
+    Ok((retried, failed)) => {
+      info!("Dead letters retried: {}, failed: {}", retried, failed);
+    }
+    Err(err) => {
+      error!("Dead letter retry failure: {}", err);
+      exit(1);
+    }
+  }
+}
+
+fn check_schema(storage: &Storage, conn: &mut Connection, migrate: bool) {
+  let schema_version = if migrate {
+    migration::migrate(conn, storage.migrations())
+  } else {
+    migration::check(conn, storage.migrations())
+  };
+  match schema_version {
+    Ok(version) => info!("Database schema version: {}", version),
+    Err(err) => {
+      error!("Database schema failure: {}", err);
+      exit(1);
+    }
+  }
+}
+
+fn init_log(log_config: &str) {
+  log4rs::init_file(log_config, Default::default()).unwrap_or_else(|err| {
+    eprintln!("Error while processing logger configuration file: {}", err);
+    exit(1);
+  });
+}
+
+fn open_storage(storage_uri: &str, options: Options) -> Arc<Storage> {
+  storage::open(storage_uri, options).unwrap_or_else(|err| {
+    eprintln!("Invalid arguments: {}", err);
+    exit(1);
+  })
 }
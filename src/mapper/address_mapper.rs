@@ -1,10 +1,13 @@
-use super::{AddressRecord, Hashes, Index, Mapper, Records, Result};
-use mysql;
+use super::{AddressRecord, Cache, DirtySet, Hashes, Index, Mapper, Records,
+            Result};
 use std::collections::{BTreeMap, HashMap};
-use std::sync::{Mutex, RwLock, RwLockWriteGuard};
+use std::sync::{Arc, Mutex, RwLock, RwLockWriteGuard};
+use storage::Connection;
 
 pub struct AddressMapper {
   counter: Mutex<u64>,
+  cache: Cache,
+  dirty_set: Arc<DirtySet>,
   records: RwLock<Records<AddressRecord>>,
   hashes: RwLock<Hashes>,
   indices: [RwLock<Records<Index>>; 0],
@@ -13,17 +16,21 @@ pub struct AddressMapper {
 impl Mapper for AddressMapper {
   type Record = AddressRecord;
 
-  fn new(conn: &mut mysql::Conn, retry_interval: u64) -> Result<Self> {
-    let counter = Self::init_counter(
-      conn,
-      retry_interval,
-      r"SELECT id_address FROM address ORDER BY id_address DESC LIMIT 1",
-    )?;
+  fn new(
+    conn: &mut Connection,
+    retry_interval: u64,
+    cache_capacity: usize,
+  ) -> Result<Self> {
+    let counter = Self::init_counter(conn, retry_interval)?;
+    let cache = Cache::new(cache_capacity);
+    let dirty_set = Arc::new(DirtySet::default());
     let records = RwLock::new(BTreeMap::new());
     let hashes = RwLock::new(HashMap::new());
     let indices = [];
     Ok(Self {
       counter,
+      cache,
+      dirty_set,
       records,
       hashes,
       indices,
@@ -34,6 +41,14 @@ impl Mapper for AddressMapper {
     &self.counter
   }
 
+  fn cache(&self) -> &Cache {
+    &self.cache
+  }
+
+  fn dirty_set(&self) -> &Arc<DirtySet> {
+    &self.dirty_set
+  }
+
   fn records(&self) -> &RwLock<Records<AddressRecord>> {
     &self.records
   }
@@ -0,0 +1,169 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub struct Cache {
+  capacity: usize,
+  lru: Mutex<Lru>,
+  hits: AtomicUsize,
+  misses: AtomicUsize,
+  evictions: AtomicUsize,
+}
+
+#[derive(Debug)]
+pub struct CacheStats {
+  pub name: &'static str,
+  pub entries: usize,
+  pub capacity: usize,
+  pub hits: usize,
+  pub misses: usize,
+  pub evictions: usize,
+}
+
+pub trait CacheMonitor: Send + Sync {
+  fn stats(&self) -> CacheStats;
+}
+
+#[derive(Default)]
+struct Lru {
+  clock: u64,
+  ticks: HashMap<u64, u64>,
+  order: BTreeMap<u64, u64>,
+}
+
+impl Cache {
+  pub fn new(capacity: usize) -> Self {
+    Self {
+      capacity,
+      lru: Mutex::new(Lru::default()),
+      hits: AtomicUsize::new(0),
+      misses: AtomicUsize::new(0),
+      evictions: AtomicUsize::new(0),
+    }
+  }
+
+  pub fn capacity(&self) -> usize {
+    self.capacity
+  }
+
+  pub fn hit(&self, id: u64) {
+    self.hits.fetch_add(1, Ordering::Relaxed);
+    self.touch(id);
+  }
+
+  pub fn miss(&self, id: u64) {
+    self.misses.fetch_add(1, Ordering::Relaxed);
+    self.touch(id);
+  }
+
+  pub fn load(&self, id: u64) {
+    self.touch(id);
+  }
+
+  pub fn evicted(&self, count: usize) {
+    self.evictions.fetch_add(count, Ordering::Relaxed);
+  }
+
+  pub fn forget(&self, ids: &[u64]) {
+    debug!("Mutex lock");
+    let mut lru = self.lru.lock().unwrap();
+    debug!("Mutex acquire");
+    let lru = &mut *lru;
+    for id in ids {
+      if let Some(tick) = lru.ticks.remove(id) {
+        lru.order.remove(&tick);
+      }
+    }
+  }
+
+  pub fn least_recent(&self) -> Vec<u64> {
+    debug!("Mutex lock");
+    let lru = self.lru.lock().unwrap();
+    debug!("Mutex acquire");
+    lru.order.values().cloned().collect()
+  }
+
+  pub fn stats(&self, name: &'static str, entries: usize) -> CacheStats {
+    CacheStats {
+      name,
+      entries,
+      capacity: self.capacity,
+      hits: self.hits.load(Ordering::Relaxed),
+      misses: self.misses.load(Ordering::Relaxed),
+      evictions: self.evictions.load(Ordering::Relaxed),
+    }
+  }
+
+  fn touch(&self, id: u64) {
+    if self.capacity == 0 {
+      return;
+    }
+    debug!("Mutex lock");
+    let mut lru = self.lru.lock().unwrap();
+    debug!("Mutex acquire");
+    let lru = &mut *lru;
+    lru.clock += 1;
+    let tick = lru.clock;
+    if let Some(previous) = lru.ticks.insert(id, tick) {
+      lru.order.remove(&previous);
+    }
+    lru.order.insert(tick, id);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn orders_least_recent_first() {
+    let cache = Cache::new(8);
+    cache.miss(1);
+    cache.load(2);
+    cache.hit(3);
+    assert_eq!(cache.least_recent(), vec![1, 2, 3]);
+    cache.hit(1);
+    assert_eq!(cache.least_recent(), vec![2, 3, 1]);
+    cache.load(2);
+    assert_eq!(cache.least_recent(), vec![3, 1, 2]);
+  }
+
+  #[test]
+  fn forgets_evicted_ids() {
+    let cache = Cache::new(8);
+    cache.load(1);
+    cache.load(2);
+    cache.load(3);
+    cache.forget(&[1, 3, 4]);
+    cache.evicted(2);
+    assert_eq!(cache.least_recent(), vec![2]);
+    cache.load(1);
+    assert_eq!(cache.least_recent(), vec![2, 1]);
+    assert_eq!(cache.stats("test", 2).evictions, 2);
+  }
+
+  #[test]
+  fn unbounded_cache_is_not_tracked() {
+    let cache = Cache::new(0);
+    cache.load(1);
+    cache.hit(1);
+    assert!(cache.least_recent().is_empty());
+    assert_eq!(cache.stats("test", 1).hits, 1);
+  }
+
+  #[test]
+  fn counts_hits_and_misses() {
+    let cache = Cache::new(8);
+    cache.miss(1);
+    cache.hit(1);
+    cache.hit(1);
+    cache.load(2);
+    let stats = cache.stats("test", 2);
+    assert_eq!(stats.name, "test");
+    assert_eq!(stats.entries, 2);
+    assert_eq!(stats.capacity, 8);
+    assert_eq!(stats.hits, 2);
+    assert_eq!(stats.misses, 1);
+    assert_eq!(stats.evictions, 0);
+  }
+}
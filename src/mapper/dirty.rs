@@ -0,0 +1,51 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+use std::time::Instant;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DirtyStats {
+  pub count: usize,
+  pub oldest: Option<Instant>,
+}
+
+#[derive(Default)]
+pub struct DirtySet {
+  inner: Mutex<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+  modified: HashMap<u64, Instant>,
+  order: BTreeSet<(Instant, u64)>,
+}
+
+impl DirtySet {
+  pub fn insert(&self, id: u64, modified_at: Instant) {
+    debug!("Mutex lock");
+    let mut inner = self.inner.lock().unwrap();
+    debug!("Mutex acquire");
+    if let Some(previous) = inner.modified.insert(id, modified_at) {
+      inner.order.remove(&(previous, id));
+    }
+    inner.order.insert((modified_at, id));
+  }
+
+  pub fn remove(&self, id: u64) {
+    debug!("Mutex lock");
+    let mut inner = self.inner.lock().unwrap();
+    debug!("Mutex acquire");
+    if let Some(modified_at) = inner.modified.remove(&id) {
+      inner.order.remove(&(modified_at, id));
+    }
+  }
+
+  pub fn stats(&self) -> DirtyStats {
+    debug!("Mutex lock");
+    let inner = self.inner.lock().unwrap();
+    debug!("Mutex acquire");
+    DirtyStats {
+      count: inner.modified.len(),
+      oldest: inner.order.iter().next().map(|&(modified_at, _)| modified_at),
+    }
+  }
+}
@@ -1,11 +1,10 @@
-use mysql;
 use std::{error, fmt, result};
+use storage;
 
 #[derive(Debug)]
 pub enum Error {
-  Mysql(mysql::Error),
+  Storage(storage::Error),
   RecordNotFound(u64),
-  ColumnNotFound,
   AddressChecksumToTrits,
 }
 
@@ -14,9 +13,8 @@ pub type Result<T> = result::Result<T, Error>;
 impl fmt::Display for Error {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match *self {
-      Error::Mysql(ref err) => write!(f, "MySQL error: {}", err),
+      Error::Storage(ref err) => write!(f, "Storage error: {}", err),
       Error::RecordNotFound(id) => write!(f, "Record not found ({})", id),
-      Error::ColumnNotFound => write!(f, "Column not found"),
       Error::AddressChecksumToTrits => {
         write!(f, "can't convert address checksum to trits")
       }
@@ -27,25 +25,22 @@ impl fmt::Display for Error {
 impl error::Error for Error {
   fn description(&self) -> &str {
     match *self {
-      Error::Mysql(ref err) => err.description(),
+      Error::Storage(ref err) => err.description(),
       Error::RecordNotFound(_) => "Record not found",
-      Error::ColumnNotFound => "Column not found",
       Error::AddressChecksumToTrits => "Can't convert to trits",
     }
   }
 
   fn cause(&self) -> Option<&error::Error> {
     match *self {
-      Error::Mysql(ref err) => Some(err),
-      Error::RecordNotFound(_) |
-      Error::ColumnNotFound |
-      Error::AddressChecksumToTrits => None,
+      Error::Storage(ref err) => Some(err),
+      Error::RecordNotFound(_) | Error::AddressChecksumToTrits => None,
     }
   }
 }
 
-impl From<mysql::Error> for Error {
-  fn from(err: mysql::Error) -> Error {
-    Error::Mysql(err)
+impl From<storage::Error> for Error {
+  fn from(err: storage::Error) -> Error {
+    Error::Storage(err)
   }
 }
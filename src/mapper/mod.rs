@@ -2,19 +2,24 @@ mod transaction_mapper;
 mod address_mapper;
 mod bundle_mapper;
 mod record;
+mod cache;
+mod dirty;
 mod error;
 
 pub use self::address_mapper::AddressMapper;
 pub use self::bundle_mapper::BundleMapper;
+pub use self::cache::{Cache, CacheMonitor, CacheStats};
+pub use self::dirty::{DirtySet, DirtyStats};
 pub use self::error::{Error, Result};
 pub use self::record::{AddressRecord, BundleRecord, Record, TransactionRecord};
 pub use self::transaction_mapper::TransactionMapper;
 
-use mysql;
+use job_queue;
 use std::collections::{BTreeMap, HashMap};
-use std::sync::{Arc, Mutex, RwLock, RwLockWriteGuard};
+use std::sync::{Arc, Mutex, PoisonError, RwLock, RwLockWriteGuard};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use storage::{Connection, Row};
 
 pub type Records<T> = BTreeMap<u64, Arc<Mutex<T>>>;
 pub type Hashes = HashMap<String, u64>;
@@ -23,10 +28,18 @@ pub type Index = Option<Vec<u64>>;
 pub trait Mapper: Sized {
   type Record: Record;
 
-  fn new(conn: &mut mysql::Conn, retry_interval: u64) -> Result<Self>;
+  fn new(
+    conn: &mut Connection,
+    retry_interval: u64,
+    cache_capacity: usize,
+  ) -> Result<Self>;
 
   fn counter(&self) -> &Mutex<u64>;
 
+  fn cache(&self) -> &Cache;
+
+  fn dirty_set(&self) -> &Arc<DirtySet>;
+
   fn records(&self) -> &RwLock<Records<Self::Record>>;
 
   fn hashes(&self) -> &RwLock<Hashes>;
@@ -48,30 +61,29 @@ pub trait Mapper: Sized {
   }
 
   fn init_counter(
-    conn: &mut mysql::Conn,
+    conn: &mut Connection,
     retry_interval: u64,
-    query: &str,
-  ) -> mysql::Result<Mutex<u64>> {
+  ) -> Result<Mutex<u64>> {
     let retry_interval = Duration::from_millis(retry_interval);
-    let row;
     loop {
-      match conn.first(query) {
-        Ok(result) => {
-          row = result;
-          break;
+      match conn.last_id(Self::Record::TABLE) {
+        Ok(id) => {
+          return Ok(Mutex::new(id.unwrap_or(0)));
         }
-        Err(mysql::Error::MySqlError(ref err)) if err.code == 1146 => {
+        Err(ref err) if err.is_missing_table() => {
           warn!("Counter initialization failure: {}. Retrying...", err);
           thread::sleep(retry_interval);
         }
         Err(err) => {
-          return Err(err);
+          return Err(err.into());
         }
       }
     }
-    row
-      .map_or_else(|| Ok(0), mysql::from_row_opt)
-      .map(Mutex::new)
+  }
+
+  fn wrap(&self, mut record: Self::Record) -> Arc<Mutex<Self::Record>> {
+    record.track(self.dirty_set().clone());
+    Arc::new(Mutex::new(record))
   }
 
   fn next_id(&self) -> u64 {
@@ -91,7 +103,7 @@ pub trait Mapper: Sized {
 
   fn fetch(
     &self,
-    conn: &mut mysql::Conn,
+    conn: &mut Connection,
     id: u64,
     skip_index: Option<(usize, u64)>,
   ) -> Result<Arc<Mutex<Self::Record>>> {
@@ -101,138 +113,376 @@ pub trait Mapper: Sized {
       debug!("Mutex acquire");
       records.get(&id).cloned()
     };
-    cached.map(Ok).unwrap_or_else(|| {
-      Self::Record::find_by_id(conn, id).map(|record| {
-        debug!("Mutex lock");
-        let mut records = self.records().write().unwrap();
-        debug!("Mutex acquire");
-        records
-          .entry(record.id())
-          .or_insert_with(|| {
-            debug!("Mutex lock");
-            let mut hashes = self.hashes().write().unwrap();
-            debug!("Mutex lock/acquire");
-            let mut indices = self.lock_indices();
-            debug!("Mutex acquire");
-            hashes.insert(record.hash().to_owned(), record.id());
-            Self::fill_indices(&mut indices, &record, skip_index);
-            Arc::new(Mutex::new(record))
-          })
-          .clone()
-      })
+    if let Some(record) = cached {
+      self.cache().hit(id);
+      return Ok(record);
+    }
+    Self::Record::find_by_id(conn, id).map(|record| {
+      self.cache().miss(record.id());
+      debug!("Mutex lock");
+      let mut records = self.records().write().unwrap();
+      debug!("Mutex acquire");
+      records
+        .entry(record.id())
+        .or_insert_with(|| {
+          debug!("Mutex lock");
+          let mut hashes = self.hashes().write().unwrap();
+          debug!("Mutex lock/acquire");
+          let mut indices = self.lock_indices();
+          debug!("Mutex acquire");
+          hashes.insert(record.hash().to_owned(), record.id());
+          Self::fill_indices(&mut indices, &record, skip_index);
+          self.wrap(record)
+        })
+        .clone()
     })
   }
 
   fn fetch_by_hash<T>(
     &self,
-    conn: &mut mysql::Conn,
+    conn: &mut Connection,
     hash: &str,
     f: T,
   ) -> Result<(u64, Arc<Mutex<Self::Record>>)>
   where
     T: FnOnce(u64) -> Result<Self::Record>,
   {
-    let cached = {
+    match self.lookup(conn, hash)? {
+      Some(cached) => Ok(cached),
+      None => Ok(self.cache_record(f(self.next_id())?)),
+    }
+  }
+
+  fn lookup(
+    &self,
+    conn: &mut Connection,
+    hash: &str,
+  ) -> Result<Option<(u64, Arc<Mutex<Self::Record>>)>> {
+    if let Some(cached) = self.cached_by_hash(hash) {
+      return Ok(Some(cached));
+    }
+    match Self::Record::find_by_hash(conn, hash)? {
+      Some(record) => Ok(Some(self.cache_record(record))),
+      None => Ok(None),
+    }
+  }
+
+  fn cache_record(
+    &self,
+    record: Self::Record,
+  ) -> (u64, Arc<Mutex<Self::Record>>) {
+    let id = record.id();
+    self.cache().miss(id);
+    debug!("Mutex lock");
+    let mut records = self.records().write().unwrap();
+    debug!("Mutex lock/acquire");
+    let mut hashes = self.hashes().write().unwrap();
+    debug!("Mutex lock/acquire");
+    let mut indices = self.lock_indices();
+    debug!("Mutex acquire");
+    let record = records.entry(id).or_insert_with(|| {
+      hashes.insert(record.hash().to_owned(), id);
+      Self::fill_indices(&mut indices, &record, None);
+      self.wrap(record)
+    });
+    (id, record.clone())
+  }
+
+  fn cached_by_hash(
+    &self,
+    hash: &str,
+  ) -> Option<(u64, Arc<Mutex<Self::Record>>)> {
+    let cached = self.peek_by_hash(hash);
+    if let Some((id, _)) = cached {
+      self.cache().hit(id);
+    }
+    cached
+  }
+
+  fn peek_by_hash(
+    &self,
+    hash: &str,
+  ) -> Option<(u64, Arc<Mutex<Self::Record>>)> {
+    debug!("Mutex lock");
+    let records = self.records().read().unwrap();
+    debug!("Mutex lock/acquire");
+    let hashes = self.hashes().read().unwrap();
+    debug!("Mutex acquire");
+    hashes
+      .get(hash)
+      .and_then(|&id| records.get(&id).map(|record| (id, record.clone())))
+  }
+
+  fn peek(&self, id: u64) -> Option<Arc<Mutex<Self::Record>>> {
+    debug!("Mutex lock");
+    let records = self.records().read().unwrap();
+    debug!("Mutex acquire");
+    records.get(&id).cloned()
+  }
+
+  fn preload(&self, conn: &mut Connection, ids: &[u64]) -> Result<usize> {
+    let records = conn
+      .find_by_ids_bulk(Self::Record::TABLE, ids)?
+      .into_iter()
+      .map(|mut row| Self::Record::from_row(&mut row))
+      .collect::<Result<Vec<_>>>()?;
+    Ok(self.cache_records(records))
+  }
+
+  fn cache_records(&self, input: Vec<Self::Record>) -> usize {
+    debug!("Mutex lock");
+    let mut records = self.records().write().unwrap();
+    debug!("Mutex lock/acquire");
+    let mut hashes = self.hashes().write().unwrap();
+    debug!("Mutex lock/acquire");
+    let mut indices = self.lock_indices();
+    debug!("Mutex acquire");
+    let mut counter = 0;
+    for record in input {
+      let id = record.id();
+      if records.contains_key(&id) {
+        continue;
+      }
+      hashes.insert(record.hash().to_owned(), id);
+      Self::fill_indices(&mut indices, &record, None);
+      records.insert(id, self.wrap(record));
+      self.cache().load(id);
+      counter += 1;
+    }
+    counter
+  }
+
+  fn update(
+    &self,
+    conn: &mut Connection,
+    batch_size: usize,
+    jobs: &[u64],
+  ) -> Result<usize> {
+    let records = {
       debug!("Mutex lock");
       let records = self.records().read().unwrap();
-      debug!("Mutex lock/acquire");
-      let hashes = self.hashes().read().unwrap();
       debug!("Mutex acquire");
-      hashes
-        .get(hash)
-        .and_then(|&id| records.get(&id).map(|record| (id, record.clone())))
+      records.values().cloned().collect::<Vec<_>>()
     };
-    cached.map(Ok).unwrap_or_else(|| {
-      Self::Record::find_by_hash(conn, hash).and_then(|record| {
+    let (rows, updated) = modified_rows(&records);
+    let result = write_rows::<Self::Record>(conn, batch_size, &rows, jobs);
+    if let Err(err) = result {
+      for (record, modified_at) in updated {
         debug!("Mutex lock");
-        let mut records = self.records().write().unwrap();
-        debug!("Mutex lock/acquire");
-        let mut hashes = self.hashes().write().unwrap();
-        debug!("Mutex lock/acquire");
-        let mut indices = self.lock_indices();
+        let mut record = record.lock().unwrap();
         debug!("Mutex acquire");
-        record.map_or_else(|| f(self.next_id()), Ok).map(|record| {
-          let id = record.id();
-          let record = records.entry(id).or_insert_with(|| {
-            hashes.insert(record.hash().to_owned(), id);
-            Self::fill_indices(&mut indices, &record, None);
-            Arc::new(Mutex::new(record))
-          });
-          (id, record.clone())
-        })
-      })
-    })
+        record.restore_modified(modified_at);
+      }
+      return Err(err);
+    }
+    Ok(updated.len())
   }
 
-  fn update(&self, conn: &mut mysql::Conn) -> Result<usize> {
-    let mut counter = 0;
-    let records = {
+  fn dirty(&self) -> DirtyStats {
+    self.dirty_set().stats()
+  }
+
+  fn prune(&self, generation_limit: usize) -> usize {
+    let ids = {
       debug!("Mutex lock");
       let records = self.records().read().unwrap();
       debug!("Mutex acquire");
-      records.values().cloned().collect::<Vec<_>>()
+      records.keys().cloned().collect::<Vec<_>>()
     };
-    for record in records {
+    self.remove_unused(ids, usize::MAX, |record| {
+      record.generation() > generation_limit
+    })
+  }
+
+  fn evict(&self) -> usize {
+    let capacity = self.cache().capacity();
+    if capacity == 0 {
+      return 0;
+    }
+    let entries = {
       debug!("Mutex lock");
-      let mut record = record.lock().unwrap();
+      let records = self.records().read().unwrap();
       debug!("Mutex acquire");
-      if !record.is_persisted() {
-        continue;
-      }
-      if record.is_modified() {
-        record.update(conn)?;
-        counter += 1;
-      }
-      record.advance_generation();
+      records.len()
+    };
+    if entries <= capacity {
+      return 0;
     }
-    Ok(counter)
+    let ids = self.cache().least_recent();
+    let evicted = self.remove_unused(ids, entries - capacity, |_| true);
+    self.cache().evicted(evicted);
+    evicted
   }
 
-  fn prune(&self, generation_limit: usize) -> usize {
-    debug!("Mutex lock");
-    let mut records = self.records().write().unwrap();
-    debug!("Mutex lock/acquire");
-    let mut hashes = self.hashes().write().unwrap();
-    debug!("Mutex lock/acquire");
-    let mut indices = self.lock_indices();
-    debug!("Mutex acquire");
-    let record_refs = records.values().cloned().collect::<Vec<_>>();
-    let index_refs = indices
-      .iter()
-      .map(|i| i.iter().map(|(&k, v)| (k, v.clone())).collect())
-      .collect::<Vec<HashMap<_, _>>>();
-    record_refs
-      .iter()
-      .filter_map(|reference| {
-        reference.try_lock().ok().map(|record| (record, reference))
-      })
-      .filter_map(|(record, reference)| {
-        let index_refs = index_refs
-          .iter()
-          .filter_map(|index| index.get(&record.id()))
-          .collect::<Vec<_>>();
-        let indices = index_refs
+  fn remove_unused<F>(&self, ids: Vec<u64>, limit: usize, filter: F) -> usize
+  where
+    F: Fn(&Self::Record) -> bool,
+  {
+    let mut removed = Vec::new();
+    {
+      debug!("Mutex lock");
+      let mut records = self.records().write().unwrap();
+      debug!("Mutex lock/acquire");
+      let mut hashes = self.hashes().write().unwrap();
+      debug!("Mutex lock/acquire");
+      let mut indices = self.lock_indices();
+      debug!("Mutex acquire");
+      for id in ids {
+        if removed.len() >= limit {
+          break;
+        }
+        let reference = match records.get(&id) {
+          Some(reference) => reference.clone(),
+          None => continue,
+        };
+        let index_refs = indices
           .iter()
-          .filter_map(|index| index.try_lock().ok())
+          .filter_map(|index| index.get(&id).cloned())
           .collect::<Vec<_>>();
-        if indices.len() == index_refs.len() &&
-          Arc::strong_count(reference) == 2 &&
-          index_refs.iter().all(|index| Arc::strong_count(index) == 2) &&
-          record.is_persisted() && !record.is_modified() &&
-          record.generation() > generation_limit
-        {
-          Some((record, indices))
-        } else {
-          None
+        let unused = {
+          let record = match reference.try_lock() {
+            Ok(record) => record,
+            Err(_) => continue,
+          };
+          let locked = index_refs
+            .iter()
+            .filter_map(|index| index.try_lock().ok())
+            .collect::<Vec<_>>();
+          if locked.len() == index_refs.len() &&
+            Arc::strong_count(&reference) == 2 &&
+            index_refs.iter().all(|index| Arc::strong_count(index) == 2) &&
+            record.is_persisted() && !record.is_modified() &&
+            filter(&record)
+          {
+            hashes.remove(record.hash());
+            true
+          } else {
+            false
+          }
+        };
+        if unused {
+          records.remove(&id);
+          for index in &mut indices {
+            index.remove(&id);
+          }
+          removed.push(id);
+        }
+      }
+    }
+    self.cache().forget(&removed);
+    removed.len()
+  }
+
+  fn purge_poisoned(&self) -> usize {
+    let mut purged = Vec::new();
+    {
+      debug!("Mutex lock");
+      let mut records = self.records().write().unwrap();
+      debug!("Mutex lock/acquire");
+      let mut hashes = self.hashes().write().unwrap();
+      debug!("Mutex lock/acquire");
+      let mut indices = self.lock_indices();
+      debug!("Mutex acquire");
+      let mut poisoned = records
+        .iter()
+        .filter(|&(_, record)| record.is_poisoned())
+        .map(|(&id, _)| id)
+        .collect::<Vec<_>>();
+      for index in &indices {
+        poisoned.extend(
+          index
+            .iter()
+            .filter(|&(_, entry)| entry.is_poisoned())
+            .map(|(&id, _)| id),
+        );
+      }
+      poisoned.sort();
+      poisoned.dedup();
+      for id in poisoned {
+        if let Some(record) = records.remove(&id) {
+          debug!("Mutex lock");
+          let record = record.lock().unwrap_or_else(PoisonError::into_inner);
+          debug!("Mutex acquire");
+          hashes.remove(record.hash());
         }
-      })
-      .for_each(|(record, _)| {
-        records.remove(&record.id());
-        hashes.remove(record.hash());
         for index in &mut indices {
-          index.remove(&record.id());
+          index.remove(&id);
         }
-      });
-    record_refs.len() - records.len()
+        self.dirty_set().remove(id);
+        purged.push(id);
+      }
+    }
+    self.cache().forget(&purged);
+    purged.len()
+  }
+
+  fn cache_stats(&self) -> CacheStats {
+    debug!("Mutex lock");
+    let entries = self.records().read().unwrap().len();
+    debug!("Mutex acquire");
+    self.cache().stats(Self::Record::TABLE.name, entries)
+  }
+}
+
+impl<T: Mapper + Send + Sync> CacheMonitor for T {
+  fn stats(&self) -> CacheStats {
+    self.cache_stats()
+  }
+}
+
+fn modified_rows<T: Record>(
+  records: &[Arc<Mutex<T>>],
+) -> (Vec<Row>, Vec<(Arc<Mutex<T>>, Instant)>) {
+  let (mut rows, mut updated) = (Vec::new(), Vec::new());
+  for record in records {
+    debug!("Mutex lock");
+    let mut guard = record.lock().unwrap();
+    debug!("Mutex acquire");
+    if !guard.is_persisted() {
+      continue;
+    }
+    if let Some(modified_at) = guard.modified_at() {
+      rows.push(guard.to_row());
+      guard.set_not_modified();
+      updated.push((record.clone(), modified_at));
+    }
+    guard.advance_generation();
+  }
+  (rows, updated)
+}
+
+fn write_rows<T: Record>(
+  conn: &mut Connection,
+  batch_size: usize,
+  rows: &[Row],
+  jobs: &[u64],
+) -> Result<()> {
+  conn.begin()?;
+  let result = update_rows::<T>(conn, batch_size, rows)
+    .and_then(|()| delete_jobs(conn, jobs))
+    .and_then(|()| Ok(conn.commit()?));
+  if result.is_err() {
+    if let Err(err) = conn.rollback() {
+      error!("Rollback failure: {}", err);
+    }
+  }
+  result
+}
+
+fn update_rows<T: Record>(
+  conn: &mut Connection,
+  batch_size: usize,
+  rows: &[Row],
+) -> Result<()> {
+  for batch in rows.chunks(batch_size.max(1)) {
+    conn.update_many(T::TABLE, batch)?;
+  }
+  Ok(())
+}
+
+fn delete_jobs(conn: &mut Connection, jobs: &[u64]) -> Result<()> {
+  for &id in jobs {
+    job_queue::delete(conn, id)?;
   }
+  Ok(())
 }
@@ -1,65 +1,45 @@
-use super::super::{Error, Record, Result};
-use mysql;
+use super::super::{DirtySet, Error, Record, Result};
+use std::sync::Arc;
+use std::time::Instant;
+use storage::{Row, Table};
 use utils;
 
 #[derive(Clone)]
 pub struct AddressRecord {
   generation: usize,
   persisted: bool,
-  modified: bool,
+  modified: Option<Instant>,
+  dirty_set: Option<Arc<DirtySet>>,
   address: String,
   id_address: u64,
   checksum: String,
 }
 
-const SELECT_QUERY: &str = r#"
-  SELECT
-    address,
-    id_address,
-    checksum
-  FROM address
-"#;
-
 impl Record for AddressRecord {
   impl_record!();
 
-  const SELECT_QUERY: &'static str = SELECT_QUERY;
-  const SELECT_WHERE_ID: &'static str = r"WHERE id_address = ?";
-  const SELECT_WHERE_HASH: &'static str = r"WHERE address = ?";
-
-  const INSERT_QUERY: &'static str = r#"
-    INSERT INTO address (
-      address,
-      id_address,
-      checksum
-    ) VALUES (
-      :address,
-      :id_address,
-      :checksum
-    )
-  "#;
-
-  const UPDATE_QUERY: &'static str = r#"
-    UPDATE address SET
-      checksum = :checksum
-    WHERE id_address = :id_address
-  "#;
+  const TABLE: &'static Table = &Table {
+    name: "address",
+    id: "id_address",
+    hash: "address",
+    columns: &["address", "id_address", "checksum"],
+    update_columns: &["checksum"],
+  };
 
-  fn from_row(row: &mut mysql::Row) -> Result<Self> {
+  fn from_row(row: &mut Row) -> Result<Self> {
     Ok(Self {
       generation: 0,
       persisted: true,
-      modified: false,
-      address: row.take_opt("address").ok_or(Error::ColumnNotFound)??,
-      id_address: row.take_opt("id_address").ok_or(Error::ColumnNotFound)??,
-      checksum: row
-        .take_opt("checksum")
-        .unwrap_or_else(|| Ok(String::from("")))?,
+      modified: None,
+      dirty_set: None,
+      address: row.take("address")?,
+      id_address: row.take("id_address")?,
+      checksum: row.take_or("checksum", String::from(""))?,
     })
   }
 
-  fn to_params(&self) -> Vec<(String, mysql::Value)> {
-    params!{
+  fn to_row(&self) -> Row {
+    row!{
       "address" => self.address.clone(),
       "id_address" => self.id_address,
       "checksum" => self.checksum.clone(),
@@ -87,7 +67,8 @@ impl AddressRecord {
     Ok(Self {
       generation: 0,
       persisted: false,
-      modified: true,
+      modified: Some(Instant::now()),
+      dirty_set: None,
       address,
       id_address,
       checksum,
@@ -1,62 +1,44 @@
-use super::super::{Error, Record, Result};
-use mysql;
+use super::super::{DirtySet, Record, Result};
+use std::sync::Arc;
+use std::time::Instant;
+use storage::{Row, Table};
 
 #[derive(Clone)]
 pub struct BundleRecord {
   generation: usize,
   persisted: bool,
-  modified: bool,
+  modified: Option<Instant>,
+  dirty_set: Option<Arc<DirtySet>>,
   bundle: String,
   id_bundle: u64,
   is_mst: bool,
 }
 
-const SELECT_QUERY: &str = r#"
-  SELECT
-    bundle,
-    id_bundle,
-    is_mst
-  FROM bundle
-"#;
-
 impl Record for BundleRecord {
   impl_record!();
 
-  const SELECT_QUERY: &'static str = SELECT_QUERY;
-  const SELECT_WHERE_ID: &'static str = r"WHERE id_bundle = ?";
-  const SELECT_WHERE_HASH: &'static str = r"WHERE bundle = ?";
-
-  const INSERT_QUERY: &'static str = r#"
-    INSERT INTO bundle (
-      bundle,
-      id_bundle,
-      is_mst
-    ) VALUES (
-      :bundle,
-      :id_bundle,
-      :is_mst
-    )
-  "#;
-
-  const UPDATE_QUERY: &'static str = r#"
-    UPDATE bundle SET
-      is_mst = :is_mst
-    WHERE id_bundle = :id_bundle
-  "#;
+  const TABLE: &'static Table = &Table {
+    name: "bundle",
+    id: "id_bundle",
+    hash: "bundle",
+    columns: &["bundle", "id_bundle", "is_mst"],
+    update_columns: &["is_mst"],
+  };
 
-  fn from_row(row: &mut mysql::Row) -> Result<Self> {
+  fn from_row(row: &mut Row) -> Result<Self> {
     Ok(Self {
       generation: 0,
       persisted: true,
-      modified: false,
-      bundle: row.take_opt("bundle").ok_or(Error::ColumnNotFound)??,
-      id_bundle: row.take_opt("id_bundle").ok_or(Error::ColumnNotFound)??,
-      is_mst: row.take_opt("is_mst").unwrap_or_else(|| Ok(false))?,
+      modified: None,
+      dirty_set: None,
+      bundle: row.take("bundle")?,
+      id_bundle: row.take("id_bundle")?,
+      is_mst: row.take_or("is_mst", false)?,
     })
   }
 
-  fn to_params(&self) -> Vec<(String, mysql::Value)> {
-    params!{
+  fn to_row(&self) -> Row {
+    row!{
       "bundle" => self.bundle.clone(),
       "id_bundle" => self.id_bundle,
       "is_mst" => self.is_mst,
@@ -81,7 +63,8 @@ impl BundleRecord {
     Self {
       generation: 0,
       persisted: false,
-      modified: true,
+      modified: Some(Instant::now()),
+      dirty_set: None,
       bundle,
       id_bundle,
       is_mst: false,
@@ -29,20 +29,53 @@ macro_rules! impl_record {
     }
 
     fn is_modified(&self) -> bool {
+      self.modified.is_some()
+    }
+
+    fn modified_at(&self) -> Option<Instant> {
       self.modified
     }
 
     fn set_persisted(&mut self, value: bool) {
       self.persisted = value;
+      self.sync_dirty();
     }
 
     fn set_modified(&mut self) {
-      self.modified = true;
+      if self.modified.is_none() {
+        self.modified = Some(Instant::now());
+        self.sync_dirty();
+      }
       self.generation = 0;
     }
 
     fn set_not_modified(&mut self) {
-      self.modified = false;
+      if self.modified.take().is_some() {
+        self.sync_dirty();
+      }
+    }
+
+    fn restore_modified(&mut self, modified_at: Instant) {
+      let modified_at = self
+        .modified
+        .map_or(modified_at, |current| current.min(modified_at));
+      self.modified = Some(modified_at);
+      self.generation = 0;
+      self.sync_dirty();
+    }
+
+    fn track(&mut self, dirty_set: Arc<DirtySet>) {
+      self.dirty_set = Some(dirty_set);
+      self.sync_dirty();
+    }
+
+    fn sync_dirty(&self) {
+      if let Some(ref dirty_set) = self.dirty_set {
+        match (self.persisted, self.modified) {
+          (true, Some(modified_at)) => dirty_set.insert(self.id(), modified_at),
+          _ => dirty_set.remove(self.id()),
+        }
+      }
     }
 
     fn advance_generation(&mut self) {
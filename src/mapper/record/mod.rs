@@ -8,19 +8,17 @@ pub use self::address_record::AddressRecord;
 pub use self::bundle_record::BundleRecord;
 pub use self::transaction_record::TransactionRecord;
 
-use super::{Error, Index, Result};
-use mysql;
+use super::{DirtySet, Error, Index, Result};
+use std::sync::Arc;
+use std::time::Instant;
+use storage::{Connection, Row, Table};
 
 pub trait Record: Sized {
-  const SELECT_QUERY: &'static str;
-  const SELECT_WHERE_ID: &'static str;
-  const SELECT_WHERE_HASH: &'static str;
-  const INSERT_QUERY: &'static str;
-  const UPDATE_QUERY: &'static str;
+  const TABLE: &'static Table;
 
-  fn from_row(row: &mut mysql::Row) -> Result<Self>;
+  fn from_row(row: &mut Row) -> Result<Self>;
 
-  fn to_params(&self) -> Vec<(String, mysql::Value)>;
+  fn to_row(&self) -> Row;
 
   fn generation(&self) -> usize;
 
@@ -28,46 +26,48 @@ pub trait Record: Sized {
 
   fn is_modified(&self) -> bool;
 
+  fn modified_at(&self) -> Option<Instant>;
+
   fn set_persisted(&mut self, value: bool);
 
   fn set_modified(&mut self);
 
   fn set_not_modified(&mut self);
 
+  fn restore_modified(&mut self, modified_at: Instant);
+
   fn advance_generation(&mut self);
 
+  fn track(&mut self, dirty_set: Arc<DirtySet>);
+
+  fn sync_dirty(&self);
+
   fn id(&self) -> u64;
 
   fn hash(&self) -> &str;
 
-  fn find_by_id(conn: &mut mysql::Conn, id: u64) -> Result<Self> {
-    Ok(conn
-      .first_exec(
-        format!("{} {}", Self::SELECT_QUERY, Self::SELECT_WHERE_ID),
-        (id,),
-      )?
+  fn find_by_id(conn: &mut Connection, id: u64) -> Result<Self> {
+    conn
+      .find_by_id(Self::TABLE, id)?
       .ok_or_else(|| Error::RecordNotFound(id))
-      .and_then(|mut row| Self::from_row(&mut row))?)
+      .and_then(|mut row| Self::from_row(&mut row))
   }
 
-  fn find_by_hash(conn: &mut mysql::Conn, hash: &str) -> Result<Option<Self>> {
-    Ok(conn
-      .first_exec(
-        format!("{} {}", Self::SELECT_QUERY, Self::SELECT_WHERE_HASH),
-        (hash,),
-      )?
-      .map_or_else(|| Ok(None), |mut row| Self::from_row(&mut row).map(Some))?)
+  fn find_by_hash(conn: &mut Connection, hash: &str) -> Result<Option<Self>> {
+    conn
+      .find_by_hash(Self::TABLE, hash)?
+      .map_or_else(|| Ok(None), |mut row| Self::from_row(&mut row).map(Some))
   }
 
-  fn insert(&mut self, conn: &mut mysql::Conn) -> Result<()> {
-    conn.prep_exec(Self::INSERT_QUERY, self.to_params())?;
-    self.set_persisted(true);
+  fn insert(&mut self, conn: &mut Connection) -> Result<()> {
+    conn.insert(Self::TABLE, &self.to_row())?;
     self.set_not_modified();
+    self.set_persisted(true);
     Ok(())
   }
 
-  fn update(&mut self, conn: &mut mysql::Conn) -> Result<()> {
-    conn.prep_exec(Self::UPDATE_QUERY, self.to_params())?;
+  fn update(&mut self, conn: &mut Connection) -> Result<()> {
+    conn.update(Self::TABLE, &self.to_row())?;
     self.set_not_modified();
     Ok(())
   }
@@ -1,12 +1,15 @@
-use super::super::{Error, Index, Record, Result};
-use mysql;
+use super::super::{DirtySet, Index, Record, Result};
 use solid::Solid;
+use std::sync::Arc;
+use std::time::Instant;
+use storage::{Connection, Row, Table};
 
 #[derive(Clone)]
 pub struct TransactionRecord {
   generation: usize,
   persisted: bool,
-  modified: bool,
+  modified: Option<Instant>,
+  dirty_set: Option<Arc<DirtySet>>,
   hash: String,
   id_tx: u64,
   id_trunk: Option<u64>,
@@ -28,141 +31,83 @@ pub struct TransactionRecord {
   solid: Solid,
 }
 
-const SELECT_QUERY: &str = r#"
-  SELECT
-    hash,
-    id_tx,
-    id_trunk,
-    id_branch,
-    id_address,
-    id_bundle,
-    tag,
-    value,
-    timestamp,
-    arrival,
-    conftime,
-    current_idx,
-    last_idx,
-    da,
-    height,
-    weight,
-    is_mst,
-    mst_a,
-    solid
-  FROM tx
-"#;
-
-const WHERE_HASH_ONE: &str = r"WHERE hash = ?";
-const WHERE_HASH_TWO: &str = r"WHERE hash IN (?, ?)";
-const WHERE_HASH_THREE: &str = r"WHERE hash IN (?, ?, ?)";
-const WHERE_ID_ONE: &str = r"WHERE id_tx = ?";
-const WHERE_ID_TWO: &str = r"WHERE id_tx IN (?, ?)";
-const WHERE_ID_FOUR: &str = r"WHERE id_tx IN (?, ?, ?, ?)";
-const WHERE_ID_EIGHT: &str = r"WHERE id_tx IN (?, ?, ?, ?, ?, ?, ?, ?)";
-const WHERE_ID_TRUNK: &str = r"WHERE id_trunk = ?";
-const WHERE_ID_BRANCH: &str = r"WHERE id_branch = ?";
-const WHERE_ID_BUNDLE: &str = r"WHERE id_bundle = ?";
-
 impl Record for TransactionRecord {
   impl_record!();
 
-  const SELECT_QUERY: &'static str = SELECT_QUERY;
-  const SELECT_WHERE_ID: &'static str = r"WHERE id_tx = ?";
-  const SELECT_WHERE_HASH: &'static str = WHERE_HASH_ONE;
+  const TABLE: &'static Table = &Table {
+    name: "tx",
+    id: "id_tx",
+    hash: "hash",
+    columns: &[
+      "hash",
+      "id_tx",
+      "id_trunk",
+      "id_branch",
+      "id_address",
+      "id_bundle",
+      "tag",
+      "value",
+      "timestamp",
+      "arrival",
+      "conftime",
+      "current_idx",
+      "last_idx",
+      "da",
+      "height",
+      "weight",
+      "is_mst",
+      "mst_a",
+      "solid",
+    ],
+    update_columns: &[
+      "id_address",
+      "id_bundle",
+      "tag",
+      "value",
+      "timestamp",
+      "arrival",
+      "conftime",
+      "current_idx",
+      "last_idx",
+      "da",
+      "height",
+      "weight",
+      "is_mst",
+      "mst_a",
+      "solid",
+    ],
+  };
 
-  const INSERT_QUERY: &'static str = r#"
-    INSERT INTO tx (
-      hash,
-      id_tx,
-      id_trunk,
-      id_branch,
-      id_address,
-      id_bundle,
-      tag,
-      value,
-      timestamp,
-      arrival,
-      conftime,
-      current_idx,
-      last_idx,
-      da,
-      height,
-      weight,
-      is_mst,
-      mst_a,
-      solid
-    ) VALUES (
-      :hash,
-      :id_tx,
-      :id_trunk,
-      :id_branch,
-      :id_address,
-      :id_bundle,
-      :tag,
-      :value,
-      :timestamp,
-      :arrival,
-      :conftime,
-      :current_idx,
-      :last_idx,
-      :da,
-      :height,
-      :weight,
-      :is_mst,
-      :mst_a,
-      :solid
-    )
-  "#;
-
-  const UPDATE_QUERY: &'static str = r#"
-    UPDATE tx SET
-      id_address = :id_address,
-      id_bundle = :id_bundle,
-      tag = :tag,
-      value = :value,
-      timestamp = :timestamp,
-      arrival = :arrival,
-      conftime = :conftime,
-      current_idx = :current_idx,
-      last_idx = :last_idx,
-      da = :da,
-      height = :height,
-      weight = :weight,
-      is_mst = :is_mst,
-      mst_a = :mst_a,
-      solid = :solid
-    WHERE id_tx = :id_tx
-  "#;
-
-  fn from_row(row: &mut mysql::Row) -> Result<Self> {
+  fn from_row(row: &mut Row) -> Result<Self> {
     Ok(Self {
       generation: 0,
       persisted: true,
-      modified: false,
-      hash: row.take_opt("hash").ok_or(Error::ColumnNotFound)??,
-      id_tx: row.take_opt("id_tx").ok_or(Error::ColumnNotFound)??,
-      id_trunk: row.take_opt("id_trunk").unwrap_or_else(|| Ok(None))?,
-      id_branch: row.take_opt("id_branch").unwrap_or_else(|| Ok(None))?,
-      id_address: row.take_opt("id_address").unwrap_or_else(|| Ok(None))?,
-      id_bundle: row.take_opt("id_bundle").unwrap_or_else(|| Ok(None))?,
-      tag: row.take_opt("tag").unwrap_or_else(|| Ok(String::from("")))?,
-      value: row.take_opt("value").unwrap_or_else(|| Ok(0))?,
-      timestamp: row.take_opt("timestamp").unwrap_or_else(|| Ok(0.0))?,
-      arrival: row.take_opt("arrival").unwrap_or_else(|| Ok(0.0))?,
-      conftime: row.take_opt("conftime").unwrap_or_else(|| Ok(0.0))?,
-      current_idx: row.take_opt("current_idx").unwrap_or_else(|| Ok(0))?,
-      last_idx: row.take_opt("last_idx").unwrap_or_else(|| Ok(0))?,
-      da: row.take_opt("da").unwrap_or_else(|| Ok(0))?,
-      height: row.take_opt("height").unwrap_or_else(|| Ok(0))?,
-      weight: row.take_opt("weight").unwrap_or_else(|| Ok(1.0))?,
-      is_mst: row.take_opt("is_mst").unwrap_or_else(|| Ok(false))?,
-      mst_a: row.take_opt("mst_a").unwrap_or_else(|| Ok(false))?,
-      solid: Solid::from_db(row.take_opt("solid").unwrap_or_else(|| Ok(0))?),
+      modified: None,
+      dirty_set: None,
+      hash: row.take("hash")?,
+      id_tx: row.take("id_tx")?,
+      id_trunk: row.take_or("id_trunk", None)?,
+      id_branch: row.take_or("id_branch", None)?,
+      id_address: row.take_or("id_address", None)?,
+      id_bundle: row.take_or("id_bundle", None)?,
+      tag: row.take_or("tag", String::from(""))?,
+      value: row.take_or("value", 0)?,
+      timestamp: row.take_or("timestamp", 0.0)?,
+      arrival: row.take_or("arrival", 0.0)?,
+      conftime: row.take_or("conftime", 0.0)?,
+      current_idx: row.take_or("current_idx", 0)?,
+      last_idx: row.take_or("last_idx", 0)?,
+      da: row.take_or("da", 0)?,
+      height: row.take_or("height", 0)?,
+      weight: row.take_or("weight", 1.0)?,
+      is_mst: row.take_or("is_mst", false)?,
+      mst_a: row.take_or("mst_a", false)?,
+      solid: Solid::from_db(row.take_or("solid", 0)?),
     })
   }
 
-  fn to_params(&self) -> Vec<(String, mysql::Value)> {
-    params!{
+  fn to_row(&self) -> Row {
+    row!{
       "hash" => self.hash.clone(),
       "id_tx" => self.id_tx,
       "id_trunk" => self.id_trunk,
@@ -219,7 +164,8 @@ impl TransactionRecord {
     Self {
       generation: 0,
       persisted: false,
-      modified: true,
+      modified: Some(Instant::now()),
+      dirty_set: None,
       hash,
       id_tx,
       id_trunk: None,
@@ -243,147 +189,74 @@ impl TransactionRecord {
   }
 
   pub fn find_by_hashes(
-    conn: &mut mysql::Conn,
+    conn: &mut Connection,
     mut hashes: Vec<&str>,
   ) -> Result<Vec<TransactionRecord>> {
     hashes.sort_unstable();
     hashes.dedup();
-    let mut results = Vec::new();
-    for hashes in hashes.chunks(3) {
-      let rows = match hashes.len() {
-        1 => conn.prep_exec(
-          format!("{} {}", SELECT_QUERY, WHERE_HASH_ONE),
-          (hashes[0],),
-        )?,
-        2 => conn.prep_exec(
-          format!("{} {}", SELECT_QUERY, WHERE_HASH_TWO),
-          (hashes[0], hashes[1]),
-        )?,
-        3 => conn.prep_exec(
-          format!("{} {}", SELECT_QUERY, WHERE_HASH_THREE),
-          (hashes[0], hashes[1], hashes[2]),
-        )?,
-        _ => unreachable!(),
-      };
-      for row in rows {
-        results.push(TransactionRecord::from_row(&mut row?)?);
-      }
-    }
-    Ok(results)
+    from_rows(conn.find_by_hashes(Self::TABLE, &hashes)?)
   }
 
   pub fn find_by_ids(
-    conn: &mut mysql::Conn,
+    conn: &mut Connection,
     ids: &[u64],
   ) -> Result<Vec<TransactionRecord>> {
-    fn collect(
-      results: &mut Vec<TransactionRecord>,
-      rows: &mut mysql::QueryResult,
-    ) -> Result<()> {
-      for row in rows {
-        results.push(TransactionRecord::from_row(&mut row?)?);
-      }
-      Ok(())
-    }
-    let mut results = Vec::new();
-    for ids in ids.chunks(8) {
-      match ids.len() {
-        8 => {
-          collect(
-            &mut results,
-            &mut conn.prep_exec(
-              format!("{} {}", SELECT_QUERY, WHERE_ID_EIGHT),
-              (
-                ids[0],
-                ids[1],
-                ids[2],
-                ids[3],
-                ids[4],
-                ids[5],
-                ids[6],
-                ids[7],
-              ),
-            )?,
-          )?;
-        }
-        _ => for ids in ids.chunks(4) {
-          match ids.len() {
-            4 => {
-              collect(
-                &mut results,
-                &mut conn.prep_exec(
-                  format!("{} {}", SELECT_QUERY, WHERE_ID_FOUR),
-                  (ids[0], ids[1], ids[2], ids[3]),
-                )?,
-              )?;
-            }
-            _ => for ids in ids.chunks(2) {
-              match ids.len() {
-                2 => {
-                  collect(
-                    &mut results,
-                    &mut conn.prep_exec(
-                      format!("{} {}", SELECT_QUERY, WHERE_ID_TWO),
-                      (ids[0], ids[1]),
-                    )?,
-                  )?;
-                }
-                1 => {
-                  collect(
-                    &mut results,
-                    &mut conn.prep_exec(
-                      format!("{} {}", SELECT_QUERY, WHERE_ID_ONE),
-                      (ids[0],),
-                    )?,
-                  )?;
-                }
-                _ => unreachable!(),
-              }
-            },
-          }
-        },
-      }
-    }
-    Ok(results)
+    from_rows(conn.find_by_ids(Self::TABLE, ids)?)
+  }
+
+  pub fn find_latest(
+    conn: &mut Connection,
+    limit: usize,
+  ) -> Result<Vec<TransactionRecord>> {
+    from_rows(conn.find_latest(Self::TABLE, limit)?)
+  }
+
+  pub fn find_trunks_from(
+    conn: &mut Connection,
+    id: u64,
+  ) -> Result<Vec<TransactionRecord>> {
+    from_rows(conn.find_by_column_from(Self::TABLE, "id_trunk", id)?)
+  }
+
+  pub fn find_branches_from(
+    conn: &mut Connection,
+    id: u64,
+  ) -> Result<Vec<TransactionRecord>> {
+    from_rows(conn.find_by_column_from(Self::TABLE, "id_branch", id)?)
+  }
+
+  pub fn find_by_address(
+    conn: &mut Connection,
+    id_address: u64,
+    limit: usize,
+  ) -> Result<Vec<TransactionRecord>> {
+    from_rows(conn.find_by_column_latest(
+      Self::TABLE,
+      "id_address",
+      id_address,
+      limit,
+    )?)
   }
 
   pub fn find_trunk(
-    conn: &mut mysql::Conn,
+    conn: &mut Connection,
     id: u64,
   ) -> Result<Vec<TransactionRecord>> {
-    let mut results = Vec::new();
-    for row in
-      conn.prep_exec(format!("{} {}", SELECT_QUERY, WHERE_ID_TRUNK), (id,))?
-    {
-      results.push(TransactionRecord::from_row(&mut row?)?);
-    }
-    Ok(results)
+    from_rows(conn.find_by_column(Self::TABLE, "id_trunk", id)?)
   }
 
   pub fn find_branch(
-    conn: &mut mysql::Conn,
+    conn: &mut Connection,
     id: u64,
   ) -> Result<Vec<TransactionRecord>> {
-    let mut results = Vec::new();
-    for row in
-      conn.prep_exec(format!("{} {}", SELECT_QUERY, WHERE_ID_BRANCH), (id,))?
-    {
-      results.push(TransactionRecord::from_row(&mut row?)?);
-    }
-    Ok(results)
+    from_rows(conn.find_by_column(Self::TABLE, "id_branch", id)?)
   }
 
   pub fn find_bundle(
-    conn: &mut mysql::Conn,
+    conn: &mut Connection,
     id: u64,
   ) -> Result<Vec<TransactionRecord>> {
-    let mut results = Vec::new();
-    for row in
-      conn.prep_exec(format!("{} {}", SELECT_QUERY, WHERE_ID_BUNDLE), (id,))?
-    {
-      results.push(TransactionRecord::from_row(&mut row?)?);
-    }
-    Ok(results)
+    from_rows(conn.find_by_column(Self::TABLE, "id_bundle", id)?)
   }
 
   pub fn mst_timestamp(&self) -> f64 {
@@ -443,3 +316,10 @@ impl TransactionRecord {
     }
   }
 }
+
+fn from_rows(rows: Vec<Row>) -> Result<Vec<TransactionRecord>> {
+  rows
+    .into_iter()
+    .map(|mut row| TransactionRecord::from_row(&mut row))
+    .collect()
+}
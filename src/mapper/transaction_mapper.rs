@@ -1,12 +1,14 @@
-use super::{Error, Hashes, Index, Mapper, Record, Records, Result,
-            TransactionRecord};
-use mysql;
+use super::{Cache, DirtySet, Error, Hashes, Index, Mapper, Record, Records,
+            Result, TransactionRecord};
 use std::collections::HashMap;
 use std::collections::btree_map::{BTreeMap, Entry};
 use std::sync::{Arc, Mutex, MutexGuard, RwLock, RwLockWriteGuard};
+use storage::Connection;
 
 pub struct TransactionMapper {
   counter: Mutex<u64>,
+  cache: Cache,
+  dirty_set: Arc<DirtySet>,
   records: RwLock<Records<TransactionRecord>>,
   hashes: RwLock<Hashes>,
   indices: [RwLock<Records<Index>>; 2],
@@ -26,17 +28,21 @@ type FetchIndexResult<'a> = Result<
 impl Mapper for TransactionMapper {
   type Record = TransactionRecord;
 
-  fn new(conn: &mut mysql::Conn, retry_interval: u64) -> Result<Self> {
-    let counter = Self::init_counter(
-      conn,
-      retry_interval,
-      r"SELECT id_tx FROM tx ORDER BY id_tx DESC LIMIT 1",
-    )?;
+  fn new(
+    conn: &mut Connection,
+    retry_interval: u64,
+    cache_capacity: usize,
+  ) -> Result<Self> {
+    let counter = Self::init_counter(conn, retry_interval)?;
+    let cache = Cache::new(cache_capacity);
+    let dirty_set = Arc::new(DirtySet::default());
     let records = RwLock::new(BTreeMap::new());
     let hashes = RwLock::new(HashMap::new());
     let indices = [RwLock::new(BTreeMap::new()), RwLock::new(BTreeMap::new())];
     Ok(Self {
       counter,
+      cache,
+      dirty_set,
       records,
       hashes,
       indices,
@@ -47,6 +53,14 @@ impl Mapper for TransactionMapper {
     &self.counter
   }
 
+  fn cache(&self) -> &Cache {
+    &self.cache
+  }
+
+  fn dirty_set(&self) -> &Arc<DirtySet> {
+    &self.dirty_set
+  }
+
   fn records(&self) -> &RwLock<Records<TransactionRecord>> {
     &self.records
   }
@@ -97,9 +111,78 @@ impl Mapper for TransactionMapper {
 }
 
 impl TransactionMapper {
+  pub fn warm_up(
+    &self,
+    conn: &mut Connection,
+    limit: usize,
+  ) -> Result<(usize, Vec<u64>, Vec<u64>)> {
+    let latest = TransactionRecord::find_latest(conn, limit)?;
+    let id_min = match latest.iter().map(|record| record.id()).min() {
+      Some(id_min) => id_min,
+      None => return Ok((0, Vec::new(), Vec::new())),
+    };
+    let trunks = TransactionRecord::find_trunks_from(conn, id_min)?;
+    let branches = TransactionRecord::find_branches_from(conn, id_min)?;
+    let mut trunk_index = latest
+      .iter()
+      .map(|record| (record.id(), Vec::new()))
+      .collect::<BTreeMap<_, _>>();
+    let mut branch_index = trunk_index.clone();
+    for record in &trunks {
+      if let Some(id_trunk) = record.id_trunk() {
+        if let Some(ids) = trunk_index.get_mut(&id_trunk) {
+          ids.push(record.id());
+        }
+      }
+    }
+    for record in &branches {
+      if let Some(id_branch) = record.id_branch() {
+        if let Some(ids) = branch_index.get_mut(&id_branch) {
+          ids.push(record.id());
+        }
+      }
+    }
+    let records = latest
+      .into_iter()
+      .chain(trunks)
+      .chain(branches)
+      .collect::<Vec<_>>();
+    let mut addresses = records
+      .iter()
+      .filter_map(|record| record.id_address())
+      .collect::<Vec<_>>();
+    let mut bundles = records
+      .iter()
+      .filter_map(|record| record.id_bundle())
+      .collect::<Vec<_>>();
+    addresses.sort_unstable();
+    addresses.dedup();
+    bundles.sort_unstable();
+    bundles.dedup();
+    let counter = self.cache_records(records);
+    for (i, index) in vec![trunk_index, branch_index].into_iter().enumerate() {
+      debug!("Mutex lock");
+      let indices = self.indices[i].read().unwrap();
+      debug!("Mutex acquire");
+      for (id, mut ids) in index {
+        if let Some(index) = indices.get(&id) {
+          debug!("Mutex lock");
+          let mut index = index.lock().unwrap();
+          debug!("Mutex acquire");
+          if index.is_none() {
+            ids.sort_unstable();
+            ids.dedup();
+            *index = Some(ids);
+          }
+        }
+      }
+    }
+    Ok((counter, addresses, bundles))
+  }
+
   pub fn fetch_many(
     &self,
-    conn: &mut mysql::Conn,
+    conn: &mut Connection,
     mut input: Vec<&str>,
   ) -> FetchManyResult {
     input.dedup();
@@ -118,6 +201,9 @@ impl TransactionMapper {
         })
         .collect::<HashMap<_, _>>()
     };
+    for &(id_tx, _) in cached.values() {
+      self.cache.hit(id_tx);
+    }
     let missing = input
       .iter()
       .filter(|&hash| !cached.contains_key(hash))
@@ -143,9 +229,10 @@ impl TransactionMapper {
             hashes
               .get(hash)
               .and_then(|&id_tx| {
-                records
-                  .get(&id_tx)
-                  .map(|record| (id_tx, hash.to_owned(), record.clone()))
+                records.get(&id_tx).map(|record| {
+                  self.cache.hit(id_tx);
+                  (id_tx, hash.to_owned(), record.clone())
+                })
               })
               .unwrap_or_else(|| {
                 let record = found
@@ -159,9 +246,10 @@ impl TransactionMapper {
                     )
                   });
                 let id_tx = record.id();
+                self.cache.miss(id_tx);
                 hashes.insert(hash.to_owned(), id_tx);
                 Self::fill_indices(&mut indices, &record, None);
-                let wrapper = Arc::new(Mutex::new(record));
+                let wrapper = self.wrap(record);
                 records.insert(id_tx, wrapper.clone());
                 (id_tx, hash.to_owned(), wrapper)
               })
@@ -188,7 +276,7 @@ impl TransactionMapper {
 
   pub fn fetch_trunk<'a>(
     &self,
-    conn: &mut mysql::Conn,
+    conn: &mut Connection,
     id: u64,
     index: &'a Mutex<Index>,
   ) -> FetchIndexResult<'a> {
@@ -203,7 +291,7 @@ impl TransactionMapper {
 
   pub fn fetch_branch<'a>(
     &self,
-    conn: &mut mysql::Conn,
+    conn: &mut Connection,
     id: u64,
     index: &'a Mutex<Index>,
   ) -> FetchIndexResult<'a> {
@@ -218,23 +306,93 @@ impl TransactionMapper {
 
   pub fn fetch_bundle<'a>(
     &self,
-    conn: &mut mysql::Conn,
+    conn: &mut Connection,
     id: u64,
     index: &'a Mutex<Index>,
   ) -> FetchIndexResult<'a> {
     self.fetch_index(conn, id, index, None, TransactionRecord::find_bundle)
   }
 
+  pub fn peek_trunk(
+    &self,
+    conn: &mut Connection,
+    id: u64,
+  ) -> Result<Vec<TransactionRecord>> {
+    self.peek_index(conn, id, 0, TransactionRecord::find_trunk)
+  }
+
+  pub fn peek_branch(
+    &self,
+    conn: &mut Connection,
+    id: u64,
+  ) -> Result<Vec<TransactionRecord>> {
+    self.peek_index(conn, id, 1, TransactionRecord::find_branch)
+  }
+
+  fn peek_index<F>(
+    &self,
+    conn: &mut Connection,
+    id: u64,
+    index: usize,
+    f: F,
+  ) -> Result<Vec<TransactionRecord>>
+  where
+    F: Fn(&mut Connection, u64) -> Result<Vec<TransactionRecord>>,
+  {
+    let index = {
+      debug!("Mutex lock");
+      let indices = self.indices[index].read().unwrap();
+      debug!("Mutex acquire");
+      indices.get(&id).cloned()
+    };
+    let ids = index.and_then(|index| {
+      debug!("Mutex lock");
+      let index = index.lock().unwrap();
+      debug!("Mutex acquire");
+      index.clone()
+    });
+    let current = |record: &Arc<Mutex<TransactionRecord>>| {
+      debug!("Mutex lock");
+      let record = record.lock().unwrap();
+      debug!("Mutex acquire");
+      record.clone()
+    };
+    let ids = match ids {
+      Some(ids) => ids,
+      None => {
+        let records = f(conn, id)?
+          .into_iter()
+          .map(|record| match self.peek(record.id()) {
+            Some(cached) => current(&cached),
+            None => record,
+          })
+          .collect();
+        return Ok(records);
+      }
+    };
+    let (mut records, mut missing) = (Vec::new(), Vec::new());
+    for id_tx in ids {
+      match self.peek(id_tx) {
+        Some(cached) => records.push(current(&cached)),
+        None => missing.push(id_tx),
+      }
+    }
+    if !missing.is_empty() {
+      records.extend(TransactionRecord::find_by_ids(conn, &missing)?);
+    }
+    Ok(records)
+  }
+
   fn fetch_index<'a, F>(
     &self,
-    conn: &mut mysql::Conn,
+    conn: &mut Connection,
     id: u64,
     index: &'a Mutex<Index>,
     skip_index: Option<(usize, u64)>,
     f: F,
   ) -> FetchIndexResult<'a>
   where
-    F: Fn(&mut mysql::Conn, u64)
+    F: Fn(&mut Connection, u64)
       -> Result<Vec<TransactionRecord>>,
   {
     'retry: loop {
@@ -258,7 +416,10 @@ impl TransactionMapper {
                 .filter_map(
                   |(id_tx, record)| record.map(|record| (id_tx, record)),
                 )
-                .collect();
+                .collect::<Vec<_>>();
+              for &(id_tx, _) in &results {
+                self.cache.hit(id_tx);
+              }
               return Ok((index, results));
             }
             results = Some(records);
@@ -289,14 +450,18 @@ impl TransactionMapper {
             if let Some(ref index) = *index {
               for &id_tx in index {
                 let record = match records.entry(id_tx) {
-                  Entry::Occupied(entry) => (id_tx, entry.get().clone()),
+                  Entry::Occupied(entry) => {
+                    self.cache.hit(id_tx);
+                    (id_tx, entry.get().clone())
+                  }
                   Entry::Vacant(entry) => {
                     match found.iter().find(|record| record.id() == id_tx) {
                       Some(record) => {
                         let id_tx = record.id();
+                        self.cache.miss(id_tx);
                         hashes.insert(record.hash().to_owned(), id_tx);
                         Self::fill_indices(&mut indices, record, skip_index);
-                        let wrapper = Arc::new(Mutex::new(record.clone()));
+                        let wrapper = self.wrap(record.clone());
                         entry.insert(wrapper.clone());
                         (id_tx, wrapper)
                       }
@@ -331,10 +496,11 @@ impl TransactionMapper {
               .into_iter()
               .map(|record| {
                 let id_tx = record.id();
+                self.cache.miss(id_tx);
                 let record = records.entry(id_tx).or_insert_with(|| {
                   hashes.insert(record.hash().to_owned(), id_tx);
                   Self::fill_indices(&mut indices, &record, skip_index);
-                  Arc::new(Mutex::new(record))
+                  self.wrap(record)
                 });
                 (id_tx, record.clone())
               })
@@ -1,6 +1,6 @@
 use mapper;
-use mysql;
 use std::{error, fmt, num, result, time};
+use storage;
 
 #[derive(Debug)]
 pub enum Error {
@@ -8,7 +8,14 @@ pub enum Error {
   ParseFloat(num::ParseFloatError),
   Mapper(mapper::Error),
   SystemTime(time::SystemTimeError),
-  Mysql(mysql::Error),
+  Storage(storage::Error),
+  UnknownTopic(String),
+  MissingField(&'static str),
+  FieldCount(usize, usize),
+  InvalidTrytes(&'static str),
+  InvalidLength(&'static str, usize, usize),
+  OutOfRange(&'static str),
+  HashMismatch(String),
 }
 
 pub type Result<T> = result::Result<T, Error>;
@@ -20,7 +27,27 @@ impl fmt::Display for Error {
       Error::ParseFloat(ref err) => write!(f, "ParseFloat error: {}", err),
       Error::Mapper(ref err) => write!(f, "Mapper error: {}", err),
       Error::SystemTime(ref err) => write!(f, "SystemTime error: {}", err),
-      Error::Mysql(ref err) => write!(f, "MySQL error: {}", err),
+      Error::Storage(ref err) => write!(f, "Storage error: {}", err),
+      Error::UnknownTopic(ref topic) => write!(f, "Unknown topic: {}", topic),
+      Error::MissingField(name) => write!(f, "Missing field: {}", name),
+      Error::FieldCount(expected, actual) => write!(
+        f,
+        "Wrong field count: expected {}, got {}",
+        expected,
+        actual
+      ),
+      Error::InvalidTrytes(name) => write!(f, "Invalid trytes: {}", name),
+      Error::InvalidLength(name, expected, actual) => write!(
+        f,
+        "Invalid length of {}: expected {}, got {}",
+        name,
+        expected,
+        actual
+      ),
+      Error::OutOfRange(name) => write!(f, "Value out of range: {}", name),
+      Error::HashMismatch(ref hash) => {
+        write!(f, "Transaction hash mismatch: {}", hash)
+      }
     }
   }
 }
@@ -32,7 +59,14 @@ impl error::Error for Error {
       Error::ParseFloat(ref err) => err.description(),
       Error::Mapper(ref err) => err.description(),
       Error::SystemTime(ref err) => err.description(),
-      Error::Mysql(ref err) => err.description(),
+      Error::Storage(ref err) => err.description(),
+      Error::UnknownTopic(_) => "Unknown topic",
+      Error::MissingField(_) => "Missing field",
+      Error::FieldCount(_, _) => "Wrong field count",
+      Error::InvalidTrytes(_) => "Invalid trytes",
+      Error::InvalidLength(_, _, _) => "Invalid length",
+      Error::OutOfRange(_) => "Value out of range",
+      Error::HashMismatch(_) => "Transaction hash mismatch",
     }
   }
 
@@ -42,7 +76,14 @@ impl error::Error for Error {
       Error::ParseFloat(ref err) => Some(err),
       Error::Mapper(ref err) => Some(err),
       Error::SystemTime(ref err) => Some(err),
-      Error::Mysql(ref err) => Some(err),
+      Error::Storage(ref err) => Some(err),
+      Error::UnknownTopic(_) |
+      Error::MissingField(_) |
+      Error::FieldCount(_, _) |
+      Error::InvalidTrytes(_) |
+      Error::InvalidLength(_, _, _) |
+      Error::OutOfRange(_) |
+      Error::HashMismatch(_) => None,
     }
   }
 }
@@ -71,8 +112,8 @@ impl From<time::SystemTimeError> for Error {
   }
 }
 
-impl From<mysql::Error> for Error {
-  fn from(err: mysql::Error) -> Error {
-    Error::Mysql(err)
+impl From<storage::Error> for Error {
+  fn from(err: storage::Error) -> Error {
+    Error::Storage(err)
   }
 }
@@ -0,0 +1,112 @@
+use super::{Error, Result};
+use std::str::FromStr;
+use utils;
+
+pub const HASH_LENGTH: usize = 81;
+
+pub fn get<'a>(
+  chunks: &[&'a str],
+  index: usize,
+  name: &'static str,
+) -> Result<&'a str> {
+  chunks
+    .get(index)
+    .cloned()
+    .ok_or_else(|| Error::MissingField(name))
+}
+
+pub fn trytes(source: &str, length: usize, name: &'static str) -> Result<()> {
+  if source.len() != length {
+    return Err(Error::InvalidLength(name, length, source.len()));
+  }
+  if !source.chars().all(utils::is_tryte) {
+    return Err(Error::InvalidTrytes(name));
+  }
+  Ok(())
+}
+
+pub fn hash(source: &str, name: &'static str) -> Result<String> {
+  trytes(source, HASH_LENGTH, name)?;
+  Ok(source.to_owned())
+}
+
+pub fn number<T>(source: &str, min: T, max: T, name: &'static str) -> Result<T>
+where
+  T: FromStr + PartialOrd,
+  Error: From<T::Err>,
+{
+  range(source.parse()?, min, max, name)
+}
+
+pub fn range<T>(number: T, min: T, max: T, name: &'static str) -> Result<T>
+where
+  T: PartialOrd,
+{
+  if !(number >= min && number <= max) {
+    return Err(Error::OutOfRange(name));
+  }
+  Ok(number)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const HASH: &str = "EMIDYNHBWMBCXVDEFOFWINXTERALUKYYPPHKP9JJFGJE\
+                      IUY9MUDVNFZHMMWZUYUSWAIOWEVTHNWMHANBH";
+
+  #[test]
+  fn accepts_numbers_within_bounds() {
+    assert_eq!(number::<u64>("0", 0, 10, "value").unwrap(), 0);
+    assert_eq!(number::<u64>("10", 0, 10, "value").unwrap(), 10);
+    assert_eq!(number::<f64>("1.5", 0.0, 2.0, "value").unwrap(), 1.5);
+    assert_eq!(range(-1i32, -1, 1, "value").unwrap(), -1);
+  }
+
+  #[test]
+  fn rejects_numbers_out_of_bounds() {
+    match number::<u64>("11", 0, 10, "value") {
+      Err(Error::OutOfRange("value")) => {}
+      result => panic!("unexpected result: {:?}", result),
+    }
+    match range(2i32, -1, 1, "value") {
+      Err(Error::OutOfRange("value")) => {}
+      result => panic!("unexpected result: {:?}", result),
+    }
+    match number::<f64>("NaN", 0.0, 2.0, "value") {
+      Err(Error::OutOfRange("value")) => {}
+      result => panic!("unexpected result: {:?}", result),
+    }
+    match number::<u64>("-1", 0, 10, "value") {
+      Err(Error::ParseInt(_)) => {}
+      result => panic!("unexpected result: {:?}", result),
+    }
+  }
+
+  #[test]
+  fn validates_trytes() {
+    assert_eq!(hash(HASH, "hash").unwrap(), HASH);
+    match trytes("ABC", 4, "tag") {
+      Err(Error::InvalidLength("tag", 4, 3)) => {}
+      result => panic!("unexpected result: {:?}", result),
+    }
+    match trytes("AbC9", 4, "tag") {
+      Err(Error::InvalidTrytes("tag")) => {}
+      result => panic!("unexpected result: {:?}", result),
+    }
+    match hash(&HASH[1..], "hash") {
+      Err(Error::InvalidLength("hash", HASH_LENGTH, 80)) => {}
+      result => panic!("unexpected result: {:?}", result),
+    }
+  }
+
+  #[test]
+  fn gets_fields_by_index() {
+    let chunks = ["tx", "hash"];
+    assert_eq!(get(&chunks, 1, "hash").unwrap(), "hash");
+    match get(&chunks, 2, "address") {
+      Err(Error::MissingField("address")) => {}
+      result => panic!("unexpected result: {:?}", result),
+    }
+  }
+}
@@ -1,5 +1,10 @@
 pub mod transaction_message;
+mod field;
+mod node_message;
+mod transaction_payload;
 mod error;
 
 pub use self::error::{Error, Result};
+pub use self::node_message::NodeMessage;
 pub use self::transaction_message::TransactionMessage;
+pub use self::transaction_payload::TransactionPayload;
@@ -0,0 +1,113 @@
+use super::{field, Error, Result};
+use std::time::SystemTime;
+use storage::{Connection, Row, Table};
+use utils::SystemTimeUtils;
+
+const MAX_INDEX: u32 = 100_000_000;
+
+const MILESTONE_TABLE: &Table = &Table {
+  name: "node_milestone",
+  id: "id_node_milestone",
+  hash: "hash",
+  columns: &[
+    "id_node_milestone",
+    "topic",
+    "previous_idx",
+    "milestone_idx",
+    "hash",
+    "timestamp",
+  ],
+  update_columns: &[],
+};
+
+const CONFIRMATION_TABLE: &Table = &Table {
+  name: "node_confirmation",
+  id: "id_node_confirmation",
+  hash: "hash",
+  columns: &["id_node_confirmation", "hash", "milestone_idx", "timestamp"],
+  update_columns: &[],
+};
+
+#[derive(Debug)]
+pub enum NodeMessage {
+  LatestMilestone { previous_index: u32, index: u32 },
+  LatestSolidMilestone { previous_index: u32, index: u32 },
+  LatestSolidMilestoneHash { hash: String },
+  Confirmed { index: u32, hash: String },
+}
+
+impl NodeMessage {
+  pub fn parse(source: &str) -> Result<Self> {
+    let chunks: Vec<&str> = source.split(' ').collect();
+    match chunks[0] {
+      "lmi" => Ok(NodeMessage::LatestMilestone {
+        previous_index: index(&chunks, 1, "previous_index")?,
+        index: index(&chunks, 2, "index")?,
+      }),
+      "lmsi" => Ok(NodeMessage::LatestSolidMilestone {
+        previous_index: index(&chunks, 1, "previous_index")?,
+        index: index(&chunks, 2, "index")?,
+      }),
+      "lmhs" => Ok(NodeMessage::LatestSolidMilestoneHash {
+        hash: field::hash(field::get(&chunks, 1, "hash")?, "hash")?,
+      }),
+      "sn" => Ok(NodeMessage::Confirmed {
+        index: index(&chunks, 1, "index")?,
+        hash: field::hash(field::get(&chunks, 2, "hash")?, "hash")?,
+      }),
+      topic => Err(Error::UnknownTopic(topic.to_owned())),
+    }
+  }
+
+  pub fn topic(&self) -> &'static str {
+    match *self {
+      NodeMessage::LatestMilestone { .. } => "lmi",
+      NodeMessage::LatestSolidMilestone { .. } => "lmsi",
+      NodeMessage::LatestSolidMilestoneHash { .. } => "lmhs",
+      NodeMessage::Confirmed { .. } => "sn",
+    }
+  }
+
+  pub fn perform(&self, conn: &mut Connection) -> Result<()> {
+    let timestamp = SystemTime::milliseconds_since_epoch()?;
+    match *self {
+      NodeMessage::LatestMilestone {
+        previous_index,
+        index,
+      } |
+      NodeMessage::LatestSolidMilestone {
+        previous_index,
+        index,
+      } => conn.insert(
+        MILESTONE_TABLE,
+        &row!{
+          "topic" => self.topic(),
+          "previous_idx" => previous_index,
+          "milestone_idx" => index,
+          "timestamp" => timestamp,
+        },
+      )?,
+      NodeMessage::LatestSolidMilestoneHash { ref hash } => conn.insert(
+        MILESTONE_TABLE,
+        &row!{
+          "topic" => self.topic(),
+          "hash" => hash.as_str(),
+          "timestamp" => timestamp,
+        },
+      )?,
+      NodeMessage::Confirmed { index, ref hash } => conn.insert(
+        CONFIRMATION_TABLE,
+        &row!{
+          "hash" => hash.as_str(),
+          "milestone_idx" => index,
+          "timestamp" => timestamp,
+        },
+      )?,
+    }
+    Ok(())
+  }
+}
+
+fn index(chunks: &[&str], position: usize, name: &'static str) -> Result<u32> {
+  field::number(field::get(chunks, position, name)?, 0, MAX_INDEX, name)
+}
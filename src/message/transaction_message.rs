@@ -1,16 +1,41 @@
-use super::Result;
+use super::{field, Error, Result, TransactionPayload};
+use curl;
 use event;
-use mapper::{AddressMapper, AddressRecord, BundleMapper, BundleRecord, Mapper,
-             Record, TransactionMapper, TransactionRecord};
-use mysql;
+use iota_trytes;
+use job_queue::{Durable, JobStore, Pending};
+use mapper::{AddressMapper, AddressRecord, BundleMapper, BundleRecord, Index,
+             Mapper, Record, TransactionMapper, TransactionRecord};
 use solid::{Solid, Solidate};
-use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
-use utils::SystemTimeUtils;
+use storage::Connection;
+use utils::{self, SystemTimeUtils};
 use worker::{ApproveJob, CalculateJob, SolidateJob};
 
 pub const TAG_LENGTH: usize = 27;
 
+const FIELD_COUNT: usize = 12;
+const MAX_SUPPLY: i64 = 2_779_530_283_277_761;
+const MAX_INDEX: i32 = 1_000_000;
+const MAX_TIMESTAMP: f64 = 1e15;
+const TRANSACTION_LENGTH: usize = 2673;
+const TRYTES_TOPIC: &str = "tx_trytes ";
+const SIGNATURE_MESSAGE_FRAGMENT: (usize, usize) = (0, 2187);
+const ADDRESS: (usize, usize) = (2187, 81);
+const VALUE: (usize, usize) = (2268, 11);
+const OBSOLETE_TAG: (usize, usize) = (2295, 27);
+const TIMESTAMP: (usize, usize) = (2322, 9);
+const CURRENT_INDEX: (usize, usize) = (2331, 9);
+const LAST_INDEX: (usize, usize) = (2340, 9);
+const BUNDLE: (usize, usize) = (2349, 81);
+const TRUNK: (usize, usize) = (2430, 81);
+const BRANCH: (usize, usize) = (2511, 81);
+const TAG: (usize, usize) = (2592, 27);
+const ATTACHMENT_TIMESTAMP: (usize, usize) = (2619, 9);
+const ATTACHMENT_TIMESTAMP_LOWER: (usize, usize) = (2628, 9);
+const ATTACHMENT_TIMESTAMP_UPPER: (usize, usize) = (2637, 9);
+const NONCE: (usize, usize) = (2646, 27);
+
 type UnwrappedTransactions<'a> = Option<
   (
     (u64, &'a Mutex<TransactionRecord>),
@@ -19,6 +44,36 @@ type UnwrappedTransactions<'a> = Option<
   ),
 >;
 
+type Jobs = (Option<ApproveJob>, Option<SolidateJob>, Option<CalculateJob>);
+
+type PendingJobs = (
+  Option<Pending<ApproveJob>>,
+  Option<Pending<SolidateJob>>,
+  Option<Pending<CalculateJob>>,
+);
+
+struct Locked<'a> {
+  trunk_index: &'a mut Index,
+  branch_index: &'a mut Index,
+  bundle_index: &'a mut Index,
+  trunk_tx: &'a mut TransactionRecord,
+  branch_tx: Option<&'a mut TransactionRecord>,
+  current_tx: &'a mut TransactionRecord,
+  address: &'a mut AddressRecord,
+  bundle: &'a mut BundleRecord,
+}
+
+struct Snapshot {
+  trunk_index: Index,
+  branch_index: Index,
+  bundle_index: Index,
+  trunk_tx: TransactionRecord,
+  branch_tx: Option<TransactionRecord>,
+  current_tx: TransactionRecord,
+  address: AddressRecord,
+  bundle: BundleRecord,
+}
+
 #[derive(Debug)]
 pub struct TransactionMessage {
   hash: String,
@@ -34,32 +89,52 @@ pub struct TransactionMessage {
   arrival: f64,
   is_mst: bool,
   solid: Solid,
+  payload: Option<TransactionPayload>,
 }
 
 impl TransactionMessage {
+  pub fn parse_topic(
+    source: &str,
+    milestone_address: &str,
+    milestone_start_index: &str,
+  ) -> Result<Self> {
+    if source.starts_with(TRYTES_TOPIC) {
+      Self::parse_trytes(source, milestone_address, milestone_start_index)
+    } else {
+      Self::parse(source, milestone_address, milestone_start_index)
+    }
+  }
+
   pub fn parse(
     source: &str,
     milestone_address: &str,
     milestone_start_index: &str,
   ) -> Result<Self> {
     let chunks: Vec<&str> = source.split(' ').collect();
-    let hash = chunks[1].to_owned();
-    let address_hash = chunks[2].to_owned();
-    let value = chunks[3].parse()?;
-    let tag = chunks[4][..TAG_LENGTH].to_owned();
-    let timestamp = chunks[5].parse()?;
-    let current_index = chunks[6].parse()?;
-    let last_index = chunks[7].parse()?;
-    let bundle_hash = chunks[8].to_owned();
-    let trunk_hash = chunks[9].to_owned();
-    let branch_hash = chunks[10].to_owned();
-    let arrival = normalize_timestamp(chunks[11].parse()?);
+    if chunks.len() < FIELD_COUNT {
+      return Err(Error::FieldCount(FIELD_COUNT, chunks.len()));
+    }
+    let hash = field::hash(chunks[1], "hash")?;
+    let address_hash = field::hash(chunks[2], "address")?;
+    let value = field::number(chunks[3], -MAX_SUPPLY, MAX_SUPPLY, "value")?;
+    let tag = chunks[4].get(..TAG_LENGTH).unwrap_or(chunks[4]);
+    field::trytes(tag, TAG_LENGTH, "tag")?;
+    let tag = tag.to_owned();
+    let timestamp = field::number(chunks[5], 0.0, MAX_TIMESTAMP, "timestamp")?;
+    let last_index = field::number(chunks[7], 0, MAX_INDEX, "last_index")?;
+    let current_index =
+      field::number(chunks[6], 0, last_index, "current_index")?;
+    let bundle_hash = field::hash(chunks[8], "bundle")?;
+    let trunk_hash = field::hash(chunks[9], "trunk")?;
+    let branch_hash = field::hash(chunks[10], "branch")?;
+    let arrival = normalize_timestamp(field::number(
+      chunks[11],
+      0.0,
+      MAX_TIMESTAMP,
+      "arrival",
+    )?);
     let is_mst = address_hash == milestone_address;
-    let solid = if is_mst && tag == milestone_start_index {
-      Solid::Complete
-    } else {
-      Solid::None
-    };
+    let solid = milestone_solid(is_mst, &tag, milestone_start_index);
     Ok(Self {
       hash,
       address_hash,
@@ -74,6 +149,75 @@ impl TransactionMessage {
       arrival,
       is_mst,
       solid,
+      payload: None,
+    })
+  }
+
+  pub fn parse_trytes(
+    source: &str,
+    milestone_address: &str,
+    milestone_start_index: &str,
+  ) -> Result<Self> {
+    let chunks: Vec<&str> = source.split(' ').collect();
+    let trytes = field::get(&chunks, 1, "trytes")?;
+    field::trytes(trytes, TRANSACTION_LENGTH, "trytes")?;
+    let trits: Vec<i8> = trytes
+      .chars()
+      .flat_map(iota_trytes::char_to_trits)
+      .cloned()
+      .collect();
+    let hash = curl::transaction_hash(&trits)
+      .ok_or_else(|| Error::InvalidTrytes("trytes"))?;
+    if let Some(&expected) = chunks.get(2) {
+      if expected != hash {
+        return Err(Error::HashMismatch(hash));
+      }
+    }
+    let string = |(offset, length): (usize, usize)| {
+      trytes[offset..offset + length].to_owned()
+    };
+    let number = |(offset, length): (usize, usize)| {
+      let start = offset * iota_trytes::TRITS_PER_TRYTE;
+      let end = (offset + length) * iota_trytes::TRITS_PER_TRYTE;
+      utils::trits_to_int(&trits[start..end])
+    };
+    let address_hash = string(ADDRESS);
+    let tag = string(TAG);
+    let value = field::range(number(VALUE), -MAX_SUPPLY, MAX_SUPPLY, "value")?;
+    let timestamp = number(TIMESTAMP) as f64;
+    let timestamp = field::range(timestamp, 0.0, MAX_TIMESTAMP, "timestamp")?;
+    let last_index = number(LAST_INDEX);
+    let last_index =
+      field::range(last_index, 0, i64::from(MAX_INDEX), "last_index")?;
+    let current_index = number(CURRENT_INDEX);
+    let current_index =
+      field::range(current_index, 0, last_index, "current_index")?;
+    let is_mst = address_hash == milestone_address;
+    let solid = milestone_solid(is_mst, &tag, milestone_start_index);
+    let arrival = normalize_timestamp(SystemTime::milliseconds_since_epoch()?);
+    let payload = TransactionPayload {
+      signature_message_fragment: string(SIGNATURE_MESSAGE_FRAGMENT),
+      obsolete_tag: string(OBSOLETE_TAG),
+      nonce: string(NONCE),
+      attachment_timestamp: number(ATTACHMENT_TIMESTAMP),
+      attachment_timestamp_lower: number(ATTACHMENT_TIMESTAMP_LOWER),
+      attachment_timestamp_upper: number(ATTACHMENT_TIMESTAMP_UPPER),
+    };
+    Ok(Self {
+      hash,
+      address_hash,
+      value,
+      tag,
+      timestamp,
+      current_index: current_index as i32,
+      last_index: last_index as i32,
+      bundle_hash: string(BUNDLE),
+      trunk_hash: string(TRUNK),
+      branch_hash: string(BRANCH),
+      arrival,
+      is_mst,
+      solid,
+      payload: Some(payload),
     })
   }
 
@@ -83,20 +227,13 @@ impl TransactionMessage {
 
   pub fn perform(
     &self,
-    conn: &mut mysql::Conn,
+    conn: &mut Connection,
     transaction_mapper: &TransactionMapper,
     address_mapper: &AddressMapper,
     bundle_mapper: &BundleMapper,
     null_hash: &str,
-  ) -> Result<
-    (
-      Option<ApproveJob>,
-      Option<SolidateJob>,
-      Option<CalculateJob>,
-    ),
-  > {
-    let (mut approve_data, mut solidate_data, mut calculate_data) =
-      (None, None, None);
+    job_store: Option<&JobStore>,
+  ) -> Result<PendingJobs> {
     let txs = transaction_mapper
       .fetch_many(conn, vec![&self.trunk_hash, &self.branch_hash, &self.hash])?;
     let (_, address) =
@@ -135,46 +272,121 @@ impl TransactionMessage {
       let mut bundle = bundle.lock().unwrap();
       debug!("Mutex acquire");
       if !current_tx.is_persisted() {
-        let timestamp = SystemTime::milliseconds_since_epoch()?;
-        process_parent(conn, null_hash, &mut trunk_tx)?;
-        current_tx.set_id_trunk(trunk_tx.id_tx(), &mut trunk_index);
-        if let Some(ref mut branch_tx) = branch_tx {
-          process_parent(conn, null_hash, branch_tx)?;
-          current_tx.set_id_branch(branch_tx.id_tx(), &mut branch_index);
-        } else {
-          current_tx.set_id_branch(trunk_tx.id_tx(), &mut branch_index);
+        let mut locked = Locked {
+          trunk_index: &mut trunk_index,
+          branch_index: &mut branch_index,
+          bundle_index: &mut bundle_index,
+          trunk_tx: &mut trunk_tx,
+          branch_tx: branch_tx.as_mut().map(|branch_tx| &mut **branch_tx),
+          current_tx: &mut current_tx,
+          address: &mut address,
+          bundle: &mut bundle,
+        };
+        let snapshot = Snapshot::take(&locked);
+        conn.begin()?;
+        let result = self
+          .insert(conn, null_hash, &mut locked)
+          .and_then(|(approve_data, solidate_data, calculate_data)| {
+            let jobs = (
+              pending(conn, job_store, approve_data)?,
+              pending(conn, job_store, solidate_data)?,
+              pending(conn, job_store, calculate_data)?,
+            );
+            conn.commit()?;
+            Ok(jobs)
+          });
+        if result.is_err() {
+          if let Err(err) = conn.rollback() {
+            error!("Rollback failure: {}", err);
+          }
+          snapshot.restore(&mut locked);
         }
-        current_tx.set_id_address(address.id_address());
-        current_tx.set_id_bundle(bundle.id_bundle(), &mut bundle_index);
-        current_tx.set_tag(self.tag.to_owned());
-        current_tx.set_value(self.value);
-        current_tx.set_timestamp(self.timestamp);
-        current_tx.set_arrival(self.arrival);
-        current_tx.set_current_idx(self.current_index);
-        current_tx.set_last_idx(self.last_index);
-        current_tx.set_is_mst(bundle.is_mst() || self.is_mst);
-        current_tx.set_mst_a(bundle.is_mst() || self.is_mst);
-        if self.is_mst {
-          bundle.set_is_mst(true);
-        }
-        self.set_solid(&mut current_tx, &trunk_tx, &branch_tx);
-        self.set_height(&mut current_tx, &trunk_tx);
-        self.insert_events(conn, &current_tx, timestamp)?;
+        return result;
+      }
+    }
+    Ok((None, None, None))
+  }
+
+  pub fn derive_jobs(
+    &self,
+    conn: &mut Connection,
+    transaction_mapper: &TransactionMapper,
+  ) -> Result<Jobs> {
+    let (mut approve_data, mut solidate_data, mut calculate_data) =
+      (None, None, None);
+    let current_tx = transaction_mapper.lookup(conn, &self.hash)?;
+    if let Some((_, current_tx)) = current_tx {
+      debug!("Mutex lock");
+      let current_tx = current_tx.lock().unwrap();
+      debug!("Mutex acquire");
+      if current_tx.is_persisted() {
         self.set_approve_data(&mut approve_data, &current_tx);
         self.set_solidate_data(&mut solidate_data, &current_tx);
         self.set_calculate_data(&mut calculate_data, &current_tx);
-        if !address.is_persisted() {
-          address.insert(conn)?;
-        }
-        if !bundle.is_persisted() {
-          bundle.insert(conn)?;
-        }
-        current_tx.insert(conn)?;
       }
     }
     Ok((approve_data, solidate_data, calculate_data))
   }
 
+  fn insert(
+    &self,
+    conn: &mut Connection,
+    null_hash: &str,
+    locked: &mut Locked,
+  ) -> Result<Jobs> {
+    let (mut approve_data, mut solidate_data, mut calculate_data) =
+      (None, None, None);
+    let timestamp = SystemTime::milliseconds_since_epoch()?;
+    let Locked {
+      ref mut trunk_index,
+      ref mut branch_index,
+      ref mut bundle_index,
+      ref mut trunk_tx,
+      ref mut branch_tx,
+      ref mut current_tx,
+      ref mut address,
+      ref mut bundle,
+    } = *locked;
+    process_parent(conn, null_hash, trunk_tx)?;
+    current_tx.set_id_trunk(trunk_tx.id_tx(), trunk_index);
+    if let Some(ref mut branch_tx) = *branch_tx {
+      process_parent(conn, null_hash, branch_tx)?;
+      current_tx.set_id_branch(branch_tx.id_tx(), branch_index);
+    } else {
+      current_tx.set_id_branch(trunk_tx.id_tx(), branch_index);
+    }
+    current_tx.set_id_address(address.id_address());
+    current_tx.set_id_bundle(bundle.id_bundle(), bundle_index);
+    current_tx.set_tag(self.tag.to_owned());
+    current_tx.set_value(self.value);
+    current_tx.set_timestamp(self.timestamp);
+    current_tx.set_arrival(self.arrival);
+    current_tx.set_current_idx(self.current_index);
+    current_tx.set_last_idx(self.last_index);
+    current_tx.set_is_mst(bundle.is_mst() || self.is_mst);
+    current_tx.set_mst_a(bundle.is_mst() || self.is_mst);
+    if self.is_mst {
+      bundle.set_is_mst(true);
+    }
+    self.set_solid(current_tx, trunk_tx, branch_tx.as_ref().map(|tx| &**tx));
+    self.set_height(current_tx, trunk_tx);
+    self.insert_events(conn, current_tx, timestamp)?;
+    self.set_approve_data(&mut approve_data, current_tx);
+    self.set_solidate_data(&mut solidate_data, current_tx);
+    self.set_calculate_data(&mut calculate_data, current_tx);
+    if !address.is_persisted() {
+      address.insert(conn)?;
+    }
+    if !bundle.is_persisted() {
+      bundle.insert(conn)?;
+    }
+    current_tx.insert(conn)?;
+    if let Some(ref payload) = self.payload {
+      payload.insert(conn, current_tx.id_tx())?;
+    }
+    Ok((approve_data, solidate_data, calculate_data))
+  }
+
   fn unwrap_transactions<'a>(
     &self,
     null_hash: &str,
@@ -202,14 +414,14 @@ impl TransactionMessage {
     &self,
     current_tx: &mut TransactionRecord,
     trunk_tx: &TransactionRecord,
-    branch_tx: &Option<MutexGuard<TransactionRecord>>,
+    branch_tx: Option<&TransactionRecord>,
   ) {
     let mut solid = self.solid;
     let mut is_complete = trunk_tx.solid().is_complete();
     if is_complete {
       solid.solidate(Solidate::Trunk);
     }
-    if let Some(ref branch_tx) = *branch_tx {
+    if let Some(branch_tx) = branch_tx {
       is_complete = branch_tx.solid().is_complete();
     }
     if is_complete {
@@ -232,7 +444,7 @@ impl TransactionMessage {
 
   fn insert_events(
     &self,
-    conn: &mut mysql::Conn,
+    conn: &mut Connection,
     current_tx: &TransactionRecord,
     timestamp: f64,
   ) -> Result<()> {
@@ -295,8 +507,45 @@ impl TransactionMessage {
   }
 }
 
+impl Snapshot {
+  fn take(locked: &Locked) -> Self {
+    Self {
+      trunk_index: locked.trunk_index.clone(),
+      branch_index: locked.branch_index.clone(),
+      bundle_index: locked.bundle_index.clone(),
+      trunk_tx: locked.trunk_tx.clone(),
+      branch_tx: locked.branch_tx.as_ref().map(|tx| (**tx).clone()),
+      current_tx: locked.current_tx.clone(),
+      address: locked.address.clone(),
+      bundle: locked.bundle.clone(),
+    }
+  }
+
+  fn restore(self, locked: &mut Locked) {
+    *locked.trunk_index = self.trunk_index;
+    *locked.branch_index = self.branch_index;
+    *locked.bundle_index = self.bundle_index;
+    *locked.trunk_tx = self.trunk_tx;
+    if let Some(ref mut branch_tx) = locked.branch_tx {
+      if let Some(snapshot) = self.branch_tx {
+        **branch_tx = snapshot;
+      }
+    }
+    *locked.current_tx = self.current_tx;
+    *locked.address = self.address;
+    *locked.bundle = self.bundle;
+    locked.trunk_tx.sync_dirty();
+    if let Some(ref branch_tx) = locked.branch_tx {
+      branch_tx.sync_dirty();
+    }
+    locked.current_tx.sync_dirty();
+    locked.address.sync_dirty();
+    locked.bundle.sync_dirty();
+  }
+}
+
 fn process_parent(
-  conn: &mut mysql::Conn,
+  conn: &mut Connection,
   null_hash: &str,
   tx: &mut TransactionRecord,
 ) -> Result<()> {
@@ -308,6 +557,33 @@ fn process_parent(
   Ok(())
 }
 
+fn pending<T: Durable>(
+  conn: &mut Connection,
+  job_store: Option<&JobStore>,
+  job: Option<T>,
+) -> Result<Option<Pending<T>>> {
+  Ok(match (job, job_store) {
+    (Some(job), Some(job_store)) => Some(Pending {
+      id: Some(job_store.insert(conn, &job)?),
+      job,
+    }),
+    (Some(job), None) => Some(Pending { id: None, job }),
+    (None, _) => None,
+  })
+}
+
+fn milestone_solid(
+  is_mst: bool,
+  tag: &str,
+  milestone_start_index: &str,
+) -> Solid {
+  if is_mst && tag == milestone_start_index {
+    Solid::Complete
+  } else {
+    Solid::None
+  }
+}
+
 fn normalize_timestamp(timestamp: f64) -> f64 {
   const THRESHOLD: f64 = 1_262_304_000_000.0; // 01.01.2010 in milliseconds
   if timestamp > THRESHOLD {
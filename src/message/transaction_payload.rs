@@ -0,0 +1,44 @@
+use storage::{Connection, Result, Row, Table};
+
+const TABLE: &Table = &Table {
+  name: "tx_payload",
+  id: "id_tx",
+  hash: "id_tx",
+  columns: &[
+    "id_tx",
+    "signature_message_fragment",
+    "obsolete_tag",
+    "nonce",
+    "attachment_timestamp",
+    "attachment_timestamp_lower",
+    "attachment_timestamp_upper",
+  ],
+  update_columns: &[],
+};
+
+#[derive(Debug)]
+pub struct TransactionPayload {
+  pub signature_message_fragment: String,
+  pub obsolete_tag: String,
+  pub nonce: String,
+  pub attachment_timestamp: i64,
+  pub attachment_timestamp_lower: i64,
+  pub attachment_timestamp_upper: i64,
+}
+
+impl TransactionPayload {
+  pub fn insert(&self, conn: &mut Connection, id_tx: u64) -> Result<()> {
+    conn.insert(
+      TABLE,
+      &row!{
+        "id_tx" => id_tx,
+        "signature_message_fragment" => self.signature_message_fragment.clone(),
+        "obsolete_tag" => self.obsolete_tag.clone(),
+        "nonce" => self.nonce.clone(),
+        "attachment_timestamp" => self.attachment_timestamp,
+        "attachment_timestamp_lower" => self.attachment_timestamp_lower,
+        "attachment_timestamp_upper" => self.attachment_timestamp_upper,
+      },
+    )
+  }
+}
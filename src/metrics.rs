@@ -0,0 +1,468 @@
+use mapper::CacheMonitor;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use storage::{Connection, Migration, Result, Row, Storage, Table};
+use worker::Monitor;
+
+const BUCKETS: &[f64] = &[
+  0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0
+];
+const STAGES: &[Stage] = &[
+  Stage::Insert,
+  Stage::Approve,
+  Stage::Solidate,
+  Stage::Calculate,
+  Stage::Update,
+];
+
+#[derive(Clone, Copy, Debug)]
+pub enum Stage {
+  Insert,
+  Approve,
+  Solidate,
+  Calculate,
+  Update,
+}
+
+pub struct Metrics {
+  messages: AtomicUsize,
+  parse_failures: AtomicUsize,
+  failures: [AtomicUsize; 5],
+  latencies: [Histogram; 5],
+  pruned: AtomicUsize,
+  storage_errors: AtomicUsize,
+  received: Mutex<Instant>,
+}
+
+pub struct MeteredStorage {
+  storage: Arc<Storage>,
+  metrics: Arc<Metrics>,
+}
+
+pub struct MeteredConnection {
+  conn: Box<Connection>,
+  metrics: Arc<Metrics>,
+}
+
+struct Histogram {
+  state: Mutex<HistogramState>,
+}
+
+#[derive(Default)]
+struct HistogramState {
+  buckets: [u64; 12],
+  count: u64,
+  sum: f64,
+}
+
+impl Stage {
+  pub fn as_str(&self) -> &'static str {
+    match *self {
+      Stage::Insert => "insert",
+      Stage::Approve => "approve",
+      Stage::Solidate => "solidate",
+      Stage::Calculate => "calculate",
+      Stage::Update => "update",
+    }
+  }
+
+  fn index(&self) -> usize {
+    *self as usize
+  }
+}
+
+impl Metrics {
+  pub fn new() -> Self {
+    Self {
+      messages: AtomicUsize::new(0),
+      parse_failures: AtomicUsize::new(0),
+      failures: [
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+        AtomicUsize::new(0),
+      ],
+      latencies: [
+        Histogram::new(),
+        Histogram::new(),
+        Histogram::new(),
+        Histogram::new(),
+        Histogram::new(),
+      ],
+      pruned: AtomicUsize::new(0),
+      storage_errors: AtomicUsize::new(0),
+      received: Mutex::new(Instant::now()),
+    }
+  }
+
+  pub fn message(&self) {
+    self.messages.fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub fn parse_failure(&self) {
+    self.parse_failures.fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub fn failure(&self, stage: Stage) {
+    self.failures[stage.index()].fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub fn observe(&self, stage: Stage, milliseconds: f64) {
+    self.latencies[stage.index()].observe(milliseconds / 1000.0);
+  }
+
+  pub fn pruned(&self, count: usize) {
+    self.pruned.fetch_add(count, Ordering::Relaxed);
+  }
+
+  pub fn storage_error(&self) {
+    self.storage_errors.fetch_add(1, Ordering::Relaxed);
+  }
+
+  pub fn received(&self) {
+    debug!("Mutex lock");
+    let mut received = self.received.lock().unwrap();
+    debug!("Mutex acquire");
+    *received = Instant::now();
+  }
+
+  pub fn idle(&self) -> Duration {
+    debug!("Mutex lock");
+    let received = self.received.lock().unwrap();
+    debug!("Mutex acquire");
+    received.elapsed()
+  }
+
+  pub fn render(
+    &self,
+    queues: &[Box<Monitor>],
+    caches: &[Arc<CacheMonitor>],
+  ) -> String {
+    let mut out = String::new();
+    header(&mut out, "messages_total", "counter", "Messages ingested");
+    sample(&mut out, "messages_total", "", load(&self.messages));
+    header(&mut out, "failures_total", "counter", "Failed messages and jobs");
+    sample(
+      &mut out,
+      "failures_total",
+      "stage=\"parse\"",
+      load(&self.parse_failures),
+    );
+    for stage in STAGES {
+      let labels = format!("stage=\"{}\"", stage.as_str());
+      let value = load(&self.failures[stage.index()]);
+      sample(&mut out, "failures_total", &labels, value);
+    }
+    header(
+      &mut out,
+      "duration_seconds",
+      "histogram",
+      "Message and job processing time",
+    );
+    for stage in STAGES {
+      self.latencies[stage.index()].render(&mut out, stage.as_str());
+    }
+    header(&mut out, "queue_depth", "gauge", "Queued items");
+    for queue in queues {
+      let stats = queue.stats();
+      let labels = format!("queue=\"{}\"", stats.name);
+      sample(&mut out, "queue_depth", &labels, stats.depth);
+    }
+    header(&mut out, "queue_capacity", "gauge", "Queue capacity");
+    for queue in queues {
+      let stats = queue.stats();
+      let labels = format!("queue=\"{}\"", stats.name);
+      sample(&mut out, "queue_capacity", &labels, stats.capacity);
+    }
+    header(&mut out, "queue_dropped_total", "counter", "Dropped queue items");
+    for queue in queues {
+      let stats = queue.stats();
+      let labels = format!("queue=\"{}\"", stats.name);
+      sample(&mut out, "queue_dropped_total", &labels, stats.dropped);
+    }
+    header(
+      &mut out,
+      "queue_coalesced_total",
+      "counter",
+      "Coalesced queue items",
+    );
+    for queue in queues {
+      let stats = queue.stats();
+      let labels = format!("queue=\"{}\"", stats.name);
+      sample(&mut out, "queue_coalesced_total", &labels, stats.coalesced);
+    }
+    let caches = caches.iter().map(|cache| cache.stats()).collect::<Vec<_>>();
+    header(&mut out, "cache_entries", "gauge", "Cached records");
+    for stats in &caches {
+      let labels = format!("cache=\"{}\"", stats.name);
+      sample(&mut out, "cache_entries", &labels, stats.entries);
+    }
+    header(&mut out, "cache_hits_total", "counter", "Cache hits");
+    for stats in &caches {
+      let labels = format!("cache=\"{}\"", stats.name);
+      sample(&mut out, "cache_hits_total", &labels, stats.hits);
+    }
+    header(&mut out, "cache_misses_total", "counter", "Cache misses");
+    for stats in &caches {
+      let labels = format!("cache=\"{}\"", stats.name);
+      sample(&mut out, "cache_misses_total", &labels, stats.misses);
+    }
+    header(&mut out, "cache_evictions_total", "counter", "Cache evictions");
+    for stats in &caches {
+      let labels = format!("cache=\"{}\"", stats.name);
+      sample(&mut out, "cache_evictions_total", &labels, stats.evictions);
+    }
+    header(&mut out, "pruned_total", "counter", "Pruned cache records");
+    sample(&mut out, "pruned_total", "", load(&self.pruned));
+    header(&mut out, "storage_errors_total", "counter", "Database errors");
+    sample(&mut out, "storage_errors_total", "", load(&self.storage_errors));
+    out
+  }
+}
+
+impl Default for Metrics {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Histogram {
+  fn new() -> Self {
+    Self {
+      state: Mutex::new(HistogramState::default()),
+    }
+  }
+
+  fn observe(&self, value: f64) {
+    debug!("Mutex lock");
+    let mut state = self.state.lock().unwrap();
+    debug!("Mutex acquire");
+    for (bucket, &bound) in state.buckets.iter_mut().zip(BUCKETS) {
+      if value <= bound {
+        *bucket += 1;
+      }
+    }
+    state.count += 1;
+    state.sum += value;
+  }
+
+  fn render(&self, out: &mut String, stage: &str) {
+    debug!("Mutex lock");
+    let state = self.state.lock().unwrap();
+    debug!("Mutex acquire");
+    for (bucket, bound) in state.buckets.iter().zip(BUCKETS) {
+      let labels = format!("stage=\"{}\",le=\"{}\"", stage, bound);
+      sample(out, "duration_seconds_bucket", &labels, bucket);
+    }
+    let labels = format!("stage=\"{}\",le=\"+Inf\"", stage);
+    sample(out, "duration_seconds_bucket", &labels, state.count);
+    let labels = format!("stage=\"{}\"", stage);
+    sample(out, "duration_seconds_sum", &labels, state.sum);
+    sample(out, "duration_seconds_count", &labels, state.count);
+  }
+}
+
+impl MeteredStorage {
+  pub fn new(storage: Arc<Storage>, metrics: Arc<Metrics>) -> Self {
+    Self { storage, metrics }
+  }
+}
+
+impl Storage for MeteredStorage {
+  fn connect(&self) -> Box<Connection> {
+    Box::new(MeteredConnection {
+      conn: self.storage.connect(),
+      metrics: self.metrics.clone(),
+    })
+  }
+
+  fn try_connect(&self) -> Result<Box<Connection>> {
+    let conn = self.storage.try_connect().map_err(|err| {
+      self.metrics.storage_error();
+      err
+    })?;
+    Ok(Box::new(MeteredConnection {
+      conn,
+      metrics: self.metrics.clone(),
+    }))
+  }
+
+  fn migrations(&self) -> &'static [Migration] {
+    self.storage.migrations()
+  }
+}
+
+impl MeteredConnection {
+  fn count<T>(&self, result: Result<T>) -> Result<T> {
+    if result.is_err() {
+      self.metrics.storage_error();
+    }
+    result
+  }
+}
+
+impl Connection for MeteredConnection {
+  fn schema_version(&mut self) -> Result<u32> {
+    let result = self.conn.schema_version();
+    self.count(result)
+  }
+
+  fn apply_migration(&mut self, migration: &Migration) -> Result<()> {
+    let result = self.conn.apply_migration(migration);
+    self.count(result)
+  }
+
+  fn begin(&mut self) -> Result<()> {
+    let result = self.conn.begin();
+    self.count(result)
+  }
+
+  fn commit(&mut self) -> Result<()> {
+    let result = self.conn.commit();
+    self.count(result)
+  }
+
+  fn rollback(&mut self) -> Result<()> {
+    let result = self.conn.rollback();
+    self.count(result)
+  }
+
+  fn last_id(&mut self, table: &Table) -> Result<Option<u64>> {
+    let result = self.conn.last_id(table);
+    self.count(result)
+  }
+
+  fn find_all(&mut self, table: &Table) -> Result<Vec<Row>> {
+    let result = self.conn.find_all(table);
+    self.count(result)
+  }
+
+  fn find_latest(&mut self, table: &Table, limit: usize) -> Result<Vec<Row>> {
+    let result = self.conn.find_latest(table, limit);
+    self.count(result)
+  }
+
+  fn find_by_id(&mut self, table: &Table, id: u64) -> Result<Option<Row>> {
+    let result = self.conn.find_by_id(table, id);
+    self.count(result)
+  }
+
+  fn find_by_ids(&mut self, table: &Table, ids: &[u64]) -> Result<Vec<Row>> {
+    let result = self.conn.find_by_ids(table, ids);
+    self.count(result)
+  }
+
+  fn find_by_ids_bulk(
+    &mut self,
+    table: &Table,
+    ids: &[u64],
+  ) -> Result<Vec<Row>> {
+    let result = self.conn.find_by_ids_bulk(table, ids);
+    self.count(result)
+  }
+
+  fn find_by_hash(&mut self, table: &Table, hash: &str) -> Result<Option<Row>> {
+    let result = self.conn.find_by_hash(table, hash);
+    self.count(result)
+  }
+
+  fn find_by_hashes(
+    &mut self,
+    table: &Table,
+    hashes: &[&str],
+  ) -> Result<Vec<Row>> {
+    let result = self.conn.find_by_hashes(table, hashes);
+    self.count(result)
+  }
+
+  fn find_by_column(
+    &mut self,
+    table: &Table,
+    column: &str,
+    id: u64,
+  ) -> Result<Vec<Row>> {
+    let result = self.conn.find_by_column(table, column, id);
+    self.count(result)
+  }
+
+  fn find_by_column_from(
+    &mut self,
+    table: &Table,
+    column: &str,
+    id: u64,
+  ) -> Result<Vec<Row>> {
+    let result = self.conn.find_by_column_from(table, column, id);
+    self.count(result)
+  }
+
+  fn find_by_column_latest(
+    &mut self,
+    table: &Table,
+    column: &str,
+    id: u64,
+    limit: usize,
+  ) -> Result<Vec<Row>> {
+    let result = self.conn.find_by_column_latest(table, column, id, limit);
+    self.count(result)
+  }
+
+  fn insert(&mut self, table: &Table, row: &Row) -> Result<()> {
+    let result = self.conn.insert(table, row);
+    self.count(result)
+  }
+
+  fn update(&mut self, table: &Table, row: &Row) -> Result<()> {
+    let result = self.conn.update(table, row);
+    self.count(result)
+  }
+
+  fn update_many(&mut self, table: &Table, rows: &[Row]) -> Result<()> {
+    let result = self.conn.update_many(table, rows);
+    self.count(result)
+  }
+
+  fn delete(&mut self, table: &Table, id: u64) -> Result<()> {
+    let result = self.conn.delete(table, id);
+    self.count(result)
+  }
+
+  fn insert_event(
+    &mut self,
+    event: &str,
+    count: i32,
+    timestamp: f64,
+  ) -> Result<()> {
+    let result = self.conn.insert_event(event, count, timestamp);
+    self.count(result)
+  }
+
+  fn event_counts(&mut self, since: f64) -> Result<Vec<Row>> {
+    let result = self.conn.event_counts(since);
+    self.count(result)
+  }
+}
+
+fn load(counter: &AtomicUsize) -> usize {
+  counter.load(Ordering::Relaxed)
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+  writeln!(out, "# HELP iota_rdb_{} {}.", name, help).unwrap();
+  writeln!(out, "# TYPE iota_rdb_{} {}", name, kind).unwrap();
+}
+
+fn sample<T: ::std::fmt::Display>(
+  out: &mut String,
+  name: &str,
+  labels: &str,
+  value: T,
+) {
+  if labels.is_empty() {
+    writeln!(out, "iota_rdb_{} {}", name, value).unwrap();
+  } else {
+    writeln!(out, "iota_rdb_{}{{{}}} {}", name, labels, value).unwrap();
+  }
+}
@@ -0,0 +1,114 @@
+use mysql;
+use postgres;
+use rusqlite;
+use std::{error, fmt, result};
+
+#[derive(Debug)]
+pub enum Error {
+  Mysql(mysql::Error),
+  Postgres(postgres::Error),
+  Sqlite(rusqlite::Error),
+  UnsupportedScheme(String),
+  InvalidIdentifier(String),
+  UnknownSchemaVersion(u32),
+  OutdatedSchemaVersion(u32, u32),
+  ColumnNotFound(String),
+  InvalidValue(String),
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+impl Error {
+  pub fn is_missing_table(&self) -> bool {
+    match *self {
+      Error::Mysql(mysql::Error::MySqlError(ref err)) => err.code == 1146,
+      Error::Postgres(ref err) => {
+        err.code().map_or(false, |code| code.code() == "42P01")
+      }
+      Error::Sqlite(rusqlite::Error::SqliteFailure(_, Some(ref message))) => {
+        message.starts_with("no such table")
+      }
+      _ => false,
+    }
+  }
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      Error::Mysql(ref err) => write!(f, "MySQL error: {}", err),
+      Error::Postgres(ref err) => write!(f, "PostgreSQL error: {}", err),
+      Error::Sqlite(ref err) => write!(f, "SQLite error: {}", err),
+      Error::UnsupportedScheme(ref scheme) => {
+        write!(f, "Unsupported storage URI scheme ({})", scheme)
+      }
+      Error::InvalidIdentifier(ref identifier) => {
+        write!(f, "Invalid database identifier ({})", identifier)
+      }
+      Error::UnknownSchemaVersion(version) => {
+        write!(f, "Unknown database schema version ({})", version)
+      }
+      Error::OutdatedSchemaVersion(version, latest) => write!(
+        f,
+        "Database schema version {} is outdated, run `migrate` to upgrade \
+         to version {}",
+        version,
+        latest
+      ),
+      Error::ColumnNotFound(ref column) => {
+        write!(f, "Column not found ({})", column)
+      }
+      Error::InvalidValue(ref column) => {
+        write!(f, "Invalid column value ({})", column)
+      }
+    }
+  }
+}
+
+impl error::Error for Error {
+  fn description(&self) -> &str {
+    match *self {
+      Error::Mysql(ref err) => err.description(),
+      Error::Postgres(ref err) => err.description(),
+      Error::Sqlite(ref err) => err.description(),
+      Error::UnsupportedScheme(_) => "Unsupported storage URI scheme",
+      Error::InvalidIdentifier(_) => "Invalid database identifier",
+      Error::UnknownSchemaVersion(_) => "Unknown database schema version",
+      Error::OutdatedSchemaVersion(_, _) => "Outdated database schema version",
+      Error::ColumnNotFound(_) => "Column not found",
+      Error::InvalidValue(_) => "Invalid column value",
+    }
+  }
+
+  fn cause(&self) -> Option<&error::Error> {
+    match *self {
+      Error::Mysql(ref err) => Some(err),
+      Error::Postgres(ref err) => Some(err),
+      Error::Sqlite(ref err) => Some(err),
+      Error::UnsupportedScheme(_) |
+      Error::InvalidIdentifier(_) |
+      Error::UnknownSchemaVersion(_) |
+      Error::OutdatedSchemaVersion(_, _) |
+      Error::ColumnNotFound(_) |
+      Error::InvalidValue(_) => None,
+    }
+  }
+}
+
+impl From<mysql::Error> for Error {
+  fn from(err: mysql::Error) -> Error {
+    Error::Mysql(err)
+  }
+}
+
+impl From<postgres::Error> for Error {
+  fn from(err: postgres::Error) -> Error {
+    Error::Postgres(err)
+  }
+}
+
+impl From<rusqlite::Error> for Error {
+  fn from(err: rusqlite::Error) -> Error {
+    Error::Sqlite(err)
+  }
+}
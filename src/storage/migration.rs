@@ -0,0 +1,57 @@
+use super::{Connection, Error, FromValue, Result, Value};
+
+const PREFIX: &str = "{prefix}";
+
+pub struct Migration {
+  pub version: u32,
+  pub name: &'static str,
+  pub sql: &'static str,
+}
+
+impl Migration {
+  pub fn render(&self, prefix: &str) -> String {
+    self.sql.replace(PREFIX, prefix)
+  }
+}
+
+pub fn latest_version(migrations: &[Migration]) -> u32 {
+  migrations.last().map_or(0, |migration| migration.version)
+}
+
+pub fn migrate(conn: &mut Connection, migrations: &[Migration]) -> Result<u32> {
+  let mut version = known_version(conn, migrations)?;
+  for migration in migrations.iter().filter(|m| m.version > version) {
+    info!("Applying migration {:03}_{}", migration.version, migration.name);
+    conn.apply_migration(migration)?;
+    version = migration.version;
+  }
+  Ok(version)
+}
+
+pub fn check(conn: &mut Connection, migrations: &[Migration]) -> Result<u32> {
+  let version = known_version(conn, migrations)?;
+  let latest = latest_version(migrations);
+  if version < latest {
+    return Err(Error::OutdatedSchemaVersion(version, latest));
+  }
+  Ok(version)
+}
+
+pub fn parse_version(value: Option<Value>) -> Result<u32> {
+  match value {
+    None | Some(Value::Null) => Ok(0),
+    Some(value) => u32::from_value(value)
+      .ok_or_else(|| Error::InvalidValue(String::from("version"))),
+  }
+}
+
+fn known_version(
+  conn: &mut Connection,
+  migrations: &[Migration],
+) -> Result<u32> {
+  let version = conn.schema_version()?;
+  if version != 0 && !migrations.iter().any(|m| m.version == version) {
+    return Err(Error::UnknownSchemaVersion(version));
+  }
+  Ok(version)
+}
@@ -0,0 +1,142 @@
+pub mod migration;
+mod mysql_storage;
+mod postgres_storage;
+mod sqlite_storage;
+mod query;
+mod row;
+mod error;
+
+pub use self::error::{Error, Result};
+pub use self::migration::Migration;
+pub use self::mysql_storage::{MysqlConnection, MysqlStorage};
+pub use self::postgres_storage::{PostgresConnection, PostgresStorage};
+pub use self::row::{FromValue, Row, Value};
+pub use self::sqlite_storage::{SqliteConnection, SqliteStorage};
+
+use std::sync::Arc;
+
+pub struct Table {
+  pub name: &'static str,
+  pub id: &'static str,
+  pub hash: &'static str,
+  pub columns: &'static [&'static str],
+  pub update_columns: &'static [&'static str],
+}
+
+#[derive(Clone)]
+pub struct Options {
+  pub database: String,
+  pub prefix: String,
+  pub retry_interval: u64,
+}
+
+pub trait Storage: Send + Sync {
+  fn connect(&self) -> Box<Connection>;
+
+  fn try_connect(&self) -> Result<Box<Connection>>;
+
+  fn migrations(&self) -> &'static [Migration];
+}
+
+pub fn open(uri: &str, options: Options) -> Result<Arc<Storage>> {
+  check_identifier(&options.database, false)?;
+  check_identifier(&options.prefix, true)?;
+  let mut parts = uri.splitn(2, "://");
+  match (parts.next(), parts.next()) {
+    (Some("mysql"), Some(_)) => Ok(Arc::new(MysqlStorage::new(uri, options))),
+    (Some("postgres"), Some(_)) | (Some("postgresql"), Some(_)) => {
+      Ok(Arc::new(PostgresStorage::new(uri, options)))
+    }
+    (Some("sqlite"), Some(path)) => {
+      Ok(Arc::new(SqliteStorage::new(path, options)?))
+    }
+    (Some(scheme), Some(_)) => Err(Error::UnsupportedScheme(scheme.to_owned())),
+    _ => Err(Error::UnsupportedScheme(String::new())),
+  }
+}
+
+fn check_identifier(identifier: &str, allow_empty: bool) -> Result<()> {
+  let valid = identifier.chars().all(|c| match c {
+    'a'...'z' | 'A'...'Z' | '0'...'9' | '_' => true,
+    _ => false,
+  });
+  if !valid || (identifier.is_empty() && !allow_empty) {
+    return Err(Error::InvalidIdentifier(identifier.to_owned()));
+  }
+  Ok(())
+}
+
+pub trait Connection: Send {
+  fn schema_version(&mut self) -> Result<u32>;
+
+  fn apply_migration(&mut self, migration: &Migration) -> Result<()>;
+
+  fn begin(&mut self) -> Result<()>;
+
+  fn commit(&mut self) -> Result<()>;
+
+  fn rollback(&mut self) -> Result<()>;
+
+  fn last_id(&mut self, table: &Table) -> Result<Option<u64>>;
+
+  fn find_all(&mut self, table: &Table) -> Result<Vec<Row>>;
+
+  fn find_latest(&mut self, table: &Table, limit: usize) -> Result<Vec<Row>>;
+
+  fn find_by_id(&mut self, table: &Table, id: u64) -> Result<Option<Row>>;
+
+  fn find_by_ids(&mut self, table: &Table, ids: &[u64]) -> Result<Vec<Row>>;
+
+  fn find_by_ids_bulk(
+    &mut self,
+    table: &Table,
+    ids: &[u64],
+  ) -> Result<Vec<Row>>;
+
+  fn find_by_hash(&mut self, table: &Table, hash: &str) -> Result<Option<Row>>;
+
+  fn find_by_hashes(
+    &mut self,
+    table: &Table,
+    hashes: &[&str],
+  ) -> Result<Vec<Row>>;
+
+  fn find_by_column(
+    &mut self,
+    table: &Table,
+    column: &str,
+    id: u64,
+  ) -> Result<Vec<Row>>;
+
+  fn find_by_column_from(
+    &mut self,
+    table: &Table,
+    column: &str,
+    id: u64,
+  ) -> Result<Vec<Row>>;
+
+  fn find_by_column_latest(
+    &mut self,
+    table: &Table,
+    column: &str,
+    id: u64,
+    limit: usize,
+  ) -> Result<Vec<Row>>;
+
+  fn insert(&mut self, table: &Table, row: &Row) -> Result<()>;
+
+  fn update(&mut self, table: &Table, row: &Row) -> Result<()>;
+
+  fn update_many(&mut self, table: &Table, rows: &[Row]) -> Result<()>;
+
+  fn delete(&mut self, table: &Table, id: u64) -> Result<()>;
+
+  fn insert_event(
+    &mut self,
+    event: &str,
+    count: i32,
+    timestamp: f64,
+  ) -> Result<()>;
+
+  fn event_counts(&mut self, since: f64) -> Result<Vec<Row>>;
+}
@@ -0,0 +1,344 @@
+use super::{migration, query, Connection, Error, FromValue, Migration,
+            Options, Result, Row, Storage, Table, Value};
+use mysql;
+use utils::MysqlConnUtils;
+
+const ID_CHUNKS: &[usize] = &[8, 4, 2, 1];
+const HASH_CHUNKS: &[usize] = &[3, 2, 1];
+const MAX_PARAMS: usize = 65535;
+const MIGRATIONS: &[Migration] = &[
+  Migration {
+    version: 1,
+    name: "initial",
+    sql: include_str!("../../db/migrations/mysql/001_initial.sql"),
+  },
+  Migration {
+    version: 2,
+    name: "node",
+    sql: include_str!("../../db/migrations/mysql/002_node.sql"),
+  },
+  Migration {
+    version: 3,
+    name: "tx_payload",
+    sql: include_str!("../../db/migrations/mysql/003_tx_payload.sql"),
+  },
+  Migration {
+    version: 4,
+    name: "failed_messages",
+    sql: include_str!("../../db/migrations/mysql/004_failed_messages.sql"),
+  },
+  Migration {
+    version: 5,
+    name: "job_queue",
+    sql: include_str!("../../db/migrations/mysql/005_job_queue.sql"),
+  },
+];
+
+pub struct MysqlStorage {
+  uri: String,
+  options: Options,
+}
+
+pub struct MysqlConnection {
+  conn: mysql::Conn,
+  prefix: String,
+}
+
+impl MysqlStorage {
+  pub fn new(uri: &str, options: Options) -> Self {
+    Self {
+      uri: uri.to_owned(),
+      options,
+    }
+  }
+}
+
+impl Storage for MysqlStorage {
+  fn connect(&self) -> Box<Connection> {
+    Box::new(MysqlConnection::new(&self.uri, &self.options))
+  }
+
+  fn try_connect(&self) -> Result<Box<Connection>> {
+    Ok(Box::new(MysqlConnection::try_new(&self.uri, &self.options)?))
+  }
+
+  fn migrations(&self) -> &'static [Migration] {
+    MIGRATIONS
+  }
+}
+
+impl MysqlConnection {
+  pub fn new(uri: &str, options: &Options) -> Self {
+    Self {
+      conn: mysql::Conn::new_retry(
+        uri,
+        &options.database,
+        options.retry_interval,
+      ),
+      prefix: options.prefix.clone(),
+    }
+  }
+
+  pub fn try_new(uri: &str, options: &Options) -> Result<Self> {
+    Ok(Self {
+      conn: mysql::Conn::new_once(uri, &options.database)?,
+      prefix: options.prefix.clone(),
+    })
+  }
+
+  fn select(
+    &mut self,
+    table: &Table,
+    query: &str,
+    params: &[Value],
+  ) -> Result<Vec<Row>> {
+    let mut results = Vec::new();
+    for row in self.conn.prep_exec(query, to_mysql_params(params))? {
+      results.push(from_mysql_row(table, row?));
+    }
+    Ok(results)
+  }
+
+  fn select_chunked<T>(
+    &mut self,
+    table: &Table,
+    column: &str,
+    sizes: &[usize],
+    values: &[T],
+  ) -> Result<Vec<Row>>
+  where
+    T: Clone + Into<Value>,
+  {
+    let mut results = Vec::new();
+    for (offset, size) in query::chunks(sizes, values.len()) {
+      let query =
+        query::select_in(placeholder, &self.prefix, table, column, size);
+      let params = values[offset..offset + size]
+        .iter()
+        .cloned()
+        .map(Into::into)
+        .collect::<Vec<_>>();
+      results.extend(self.select(table, &query, &params)?);
+    }
+    Ok(results)
+  }
+
+  fn execute(&mut self, query: &str, params: &[Value]) -> Result<()> {
+    self.conn.prep_exec(query, to_mysql_params(params))?;
+    Ok(())
+  }
+
+  fn scalar(&mut self, query: &str) -> Result<Option<Value>> {
+    Ok(self.conn.first(query)?.and_then(|row| {
+      row.unwrap().into_iter().next().map(from_mysql_value)
+    }))
+  }
+}
+
+impl Connection for MysqlConnection {
+  fn schema_version(&mut self) -> Result<u32> {
+    match self.scalar(&query::schema_version(&self.prefix)) {
+      Ok(value) => migration::parse_version(value),
+      Err(ref err) if err.is_missing_table() => Ok(0),
+      Err(err) => Err(err),
+    }
+  }
+
+  fn apply_migration(&mut self, migration: &Migration) -> Result<()> {
+    self.conn.query(migration.render(&self.prefix))?;
+    self.execute(
+      &query::insert_migration(placeholder, &self.prefix),
+      &[Value::from(migration.version), Value::from(migration.name)],
+    )
+  }
+
+  fn begin(&mut self) -> Result<()> {
+    self.conn.query("START TRANSACTION")?;
+    Ok(())
+  }
+
+  fn commit(&mut self) -> Result<()> {
+    self.conn.query("COMMIT")?;
+    Ok(())
+  }
+
+  fn rollback(&mut self) -> Result<()> {
+    self.conn.query("ROLLBACK")?;
+    Ok(())
+  }
+
+  fn last_id(&mut self, table: &Table) -> Result<Option<u64>> {
+    match self.scalar(&query::last_id(&self.prefix, table))? {
+      Some(value) => u64::from_value(value)
+        .map(Some)
+        .ok_or_else(|| Error::InvalidValue(table.id.to_owned())),
+      None => Ok(None),
+    }
+  }
+
+  fn find_all(&mut self, table: &Table) -> Result<Vec<Row>> {
+    let query = query::select_all(&self.prefix, table);
+    self.select(table, &query, &[])
+  }
+
+  fn find_latest(&mut self, table: &Table, limit: usize) -> Result<Vec<Row>> {
+    let query = query::select_latest(&self.prefix, table, limit);
+    self.select(table, &query, &[])
+  }
+
+  fn find_by_id(&mut self, table: &Table, id: u64) -> Result<Option<Row>> {
+    let query = query::select_eq(placeholder, &self.prefix, table, table.id);
+    Ok(self.select(table, &query, &[Value::from(id)])?.pop())
+  }
+
+  fn find_by_ids(&mut self, table: &Table, ids: &[u64]) -> Result<Vec<Row>> {
+    self.select_chunked(table, table.id, ID_CHUNKS, ids)
+  }
+
+  fn find_by_ids_bulk(
+    &mut self,
+    table: &Table,
+    ids: &[u64],
+  ) -> Result<Vec<Row>> {
+    let mut results = Vec::new();
+    for ids in ids.chunks(MAX_PARAMS) {
+      results.extend(self.select_chunked(table, table.id, &[ids.len()], ids)?);
+    }
+    Ok(results)
+  }
+
+  fn find_by_hash(&mut self, table: &Table, hash: &str) -> Result<Option<Row>> {
+    let query = query::select_eq(placeholder, &self.prefix, table, table.hash);
+    Ok(self.select(table, &query, &[Value::from(hash)])?.pop())
+  }
+
+  fn find_by_hashes(
+    &mut self,
+    table: &Table,
+    hashes: &[&str],
+  ) -> Result<Vec<Row>> {
+    self.select_chunked(table, table.hash, HASH_CHUNKS, hashes)
+  }
+
+  fn find_by_column(
+    &mut self,
+    table: &Table,
+    column: &str,
+    id: u64,
+  ) -> Result<Vec<Row>> {
+    let query = query::select_eq(placeholder, &self.prefix, table, column);
+    self.select(table, &query, &[Value::from(id)])
+  }
+
+  fn find_by_column_from(
+    &mut self,
+    table: &Table,
+    column: &str,
+    id: u64,
+  ) -> Result<Vec<Row>> {
+    let query = query::select_from(placeholder, &self.prefix, table, column);
+    self.select(table, &query, &[Value::from(id)])
+  }
+
+  fn find_by_column_latest(
+    &mut self,
+    table: &Table,
+    column: &str,
+    id: u64,
+    limit: usize,
+  ) -> Result<Vec<Row>> {
+    let query =
+      query::select_eq_latest(placeholder, &self.prefix, table, column, limit);
+    self.select(table, &query, &[Value::from(id)])
+  }
+
+  fn insert(&mut self, table: &Table, row: &Row) -> Result<()> {
+    let params = row
+      .values()
+      .iter()
+      .map(|&(_, ref value)| value.clone())
+      .collect::<Vec<_>>();
+    let query = query::insert(placeholder, &self.prefix, table, row);
+    self.execute(&query, &params)
+  }
+
+  fn update(&mut self, table: &Table, row: &Row) -> Result<()> {
+    let params = query::update_params(table, row);
+    let query = query::update(placeholder, &self.prefix, table);
+    self.execute(&query, &params)
+  }
+
+  fn update_many(&mut self, table: &Table, rows: &[Row]) -> Result<()> {
+    for rows in rows.chunks(query::rows_per_statement(table, MAX_PARAMS)) {
+      let params = query::update_many_params(table, rows);
+      let query =
+        query::update_many(placeholder, &self.prefix, table, rows.len());
+      self.execute(&query, &params)?;
+    }
+    Ok(())
+  }
+
+  fn delete(&mut self, table: &Table, id: u64) -> Result<()> {
+    let query = query::delete(placeholder, &self.prefix, table);
+    self.execute(&query, &[Value::from(id)])
+  }
+
+  fn insert_event(
+    &mut self,
+    event: &str,
+    count: i32,
+    timestamp: f64,
+  ) -> Result<()> {
+    self.execute(
+      &query::insert_event(placeholder, &self.prefix),
+      &[Value::from(event), Value::from(count), Value::from(timestamp)],
+    )
+  }
+
+  fn event_counts(&mut self, since: f64) -> Result<Vec<Row>> {
+    let query = query::select_event_counts(placeholder, &self.prefix);
+    self.select(query::EVENT_COUNTS, &query, &[Value::from(since)])
+  }
+}
+
+fn placeholder(_index: usize) -> String {
+  String::from("?")
+}
+
+fn to_mysql_params(params: &[Value]) -> mysql::Params {
+  if params.is_empty() {
+    mysql::Params::Empty
+  } else {
+    mysql::Params::Positional(params.iter().map(to_mysql_value).collect())
+  }
+}
+
+fn to_mysql_value(value: &Value) -> mysql::Value {
+  match *value {
+    Value::Null => mysql::Value::NULL,
+    Value::Int(value) => mysql::Value::Int(value),
+    Value::Float(value) => mysql::Value::Float(value),
+    Value::Text(ref value) => mysql::Value::Bytes(value.clone().into_bytes()),
+  }
+}
+
+fn from_mysql_value(value: mysql::Value) -> Value {
+  match value {
+    mysql::Value::NULL => Value::Null,
+    mysql::Value::Int(value) => Value::Int(value),
+    mysql::Value::UInt(value) => Value::Int(value as i64),
+    mysql::Value::Float(value) => Value::Float(value),
+    mysql::Value::Bytes(value) => {
+      Value::Text(String::from_utf8_lossy(&value).into_owned())
+    }
+    value => Value::Text(value.as_sql(true)),
+  }
+}
+
+fn from_mysql_row(table: &Table, row: mysql::Row) -> Row {
+  let mut result = Row::new();
+  for (&column, value) in table.columns.iter().zip(row.unwrap()) {
+    result.push(column, from_mysql_value(value));
+  }
+  result
+}
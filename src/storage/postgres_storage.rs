@@ -0,0 +1,382 @@
+use super::{migration, query, Connection, Error, FromValue, Migration,
+            Options, Result, Row, Storage, Table, Value};
+use postgres::{self, types};
+use postgres::types::{FromSql, IsNull, ToSql};
+use std::error;
+use utils::PostgresConnUtils;
+
+const ID_CHUNKS: &[usize] = &[8, 4, 2, 1];
+const HASH_CHUNKS: &[usize] = &[3, 2, 1];
+const MAX_PARAMS: usize = 32767;
+const MIGRATIONS: &[Migration] = &[
+  Migration {
+    version: 1,
+    name: "initial",
+    sql: include_str!("../../db/migrations/postgres/001_initial.sql"),
+  },
+  Migration {
+    version: 2,
+    name: "node",
+    sql: include_str!("../../db/migrations/postgres/002_node.sql"),
+  },
+  Migration {
+    version: 3,
+    name: "tx_payload",
+    sql: include_str!("../../db/migrations/postgres/003_tx_payload.sql"),
+  },
+  Migration {
+    version: 4,
+    name: "failed_messages",
+    sql: include_str!("../../db/migrations/postgres/004_failed_messages.sql"),
+  },
+  Migration {
+    version: 5,
+    name: "job_queue",
+    sql: include_str!("../../db/migrations/postgres/005_job_queue.sql"),
+  },
+];
+
+type SqlResult<T> = ::std::result::Result<T, Box<error::Error + Sync + Send>>;
+
+pub struct PostgresStorage {
+  uri: String,
+  options: Options,
+}
+
+pub struct PostgresConnection {
+  conn: postgres::Connection,
+  prefix: String,
+}
+
+impl PostgresStorage {
+  pub fn new(uri: &str, options: Options) -> Self {
+    Self {
+      uri: uri.to_owned(),
+      options,
+    }
+  }
+}
+
+impl Storage for PostgresStorage {
+  fn connect(&self) -> Box<Connection> {
+    Box::new(PostgresConnection::new(&self.uri, &self.options))
+  }
+
+  fn try_connect(&self) -> Result<Box<Connection>> {
+    Ok(Box::new(PostgresConnection::try_new(&self.uri, &self.options)?))
+  }
+
+  fn migrations(&self) -> &'static [Migration] {
+    MIGRATIONS
+  }
+}
+
+impl PostgresConnection {
+  pub fn new(uri: &str, options: &Options) -> Self {
+    Self {
+      conn: postgres::Connection::new_retry(
+        uri,
+        &options.database,
+        options.retry_interval,
+      ),
+      prefix: options.prefix.clone(),
+    }
+  }
+
+  pub fn try_new(uri: &str, options: &Options) -> Result<Self> {
+    Ok(Self {
+      conn: postgres::Connection::new_once(uri, &options.database)?,
+      prefix: options.prefix.clone(),
+    })
+  }
+
+  fn select(
+    &mut self,
+    table: &Table,
+    query: &str,
+    params: &[Value],
+  ) -> Result<Vec<Row>> {
+    let params = params.iter().map(|x| x as &ToSql).collect::<Vec<_>>();
+    let stmt = self.conn.prepare_cached(query)?;
+    let rows = stmt.query(&params)?;
+    let mut results = Vec::new();
+    for row in &rows {
+      let mut result = Row::new();
+      for (i, &column) in table.columns.iter().enumerate() {
+        let value = row.get_opt::<_, Value>(i).unwrap_or(Ok(Value::Null))?;
+        result.push(column, value);
+      }
+      results.push(result);
+    }
+    Ok(results)
+  }
+
+  fn select_chunked<T>(
+    &mut self,
+    table: &Table,
+    column: &str,
+    sizes: &[usize],
+    values: &[T],
+  ) -> Result<Vec<Row>>
+  where
+    T: Clone + Into<Value>,
+  {
+    let mut results = Vec::new();
+    for (offset, size) in query::chunks(sizes, values.len()) {
+      let query =
+        query::select_in(placeholder, &self.prefix, table, column, size);
+      let params = values[offset..offset + size]
+        .iter()
+        .cloned()
+        .map(Into::into)
+        .collect::<Vec<_>>();
+      results.extend(self.select(table, &query, &params)?);
+    }
+    Ok(results)
+  }
+
+  fn execute(&mut self, query: &str, params: &[Value]) -> Result<()> {
+    let params = params.iter().map(|x| x as &ToSql).collect::<Vec<_>>();
+    self.conn.prepare_cached(query)?.execute(&params)?;
+    Ok(())
+  }
+
+  fn scalar(&mut self, query: &str) -> Result<Option<Value>> {
+    let stmt = self.conn.prepare_cached(query)?;
+    let rows = stmt.query(&[])?;
+    let value = match rows.iter().next() {
+      Some(row) => Some(row.get_opt::<_, Value>(0).unwrap_or(Ok(Value::Null))?),
+      None => None,
+    };
+    Ok(value)
+  }
+}
+
+impl Connection for PostgresConnection {
+  fn schema_version(&mut self) -> Result<u32> {
+    match self.scalar(&query::schema_version(&self.prefix)) {
+      Ok(value) => migration::parse_version(value),
+      Err(ref err) if err.is_missing_table() => Ok(0),
+      Err(err) => Err(err),
+    }
+  }
+
+  fn apply_migration(&mut self, migration: &Migration) -> Result<()> {
+    let version = Value::from(migration.version);
+    let name = Value::from(migration.name);
+    let transaction = self.conn.transaction()?;
+    transaction.batch_execute(&migration.render(&self.prefix))?;
+    transaction.execute(
+      &query::insert_migration(placeholder, &self.prefix),
+      &[&version as &ToSql, &name],
+    )?;
+    transaction.commit()?;
+    Ok(())
+  }
+
+  fn begin(&mut self) -> Result<()> {
+    self.conn.batch_execute("BEGIN")?;
+    Ok(())
+  }
+
+  fn commit(&mut self) -> Result<()> {
+    self.conn.batch_execute("COMMIT")?;
+    Ok(())
+  }
+
+  fn rollback(&mut self) -> Result<()> {
+    self.conn.batch_execute("ROLLBACK")?;
+    Ok(())
+  }
+
+  fn last_id(&mut self, table: &Table) -> Result<Option<u64>> {
+    match self.scalar(&query::last_id(&self.prefix, table))? {
+      Some(value) => u64::from_value(value)
+        .map(Some)
+        .ok_or_else(|| Error::InvalidValue(table.id.to_owned())),
+      None => Ok(None),
+    }
+  }
+
+  fn find_all(&mut self, table: &Table) -> Result<Vec<Row>> {
+    let query = query::select_all(&self.prefix, table);
+    self.select(table, &query, &[])
+  }
+
+  fn find_latest(&mut self, table: &Table, limit: usize) -> Result<Vec<Row>> {
+    let query = query::select_latest(&self.prefix, table, limit);
+    self.select(table, &query, &[])
+  }
+
+  fn find_by_id(&mut self, table: &Table, id: u64) -> Result<Option<Row>> {
+    let query = query::select_eq(placeholder, &self.prefix, table, table.id);
+    Ok(self.select(table, &query, &[Value::from(id)])?.pop())
+  }
+
+  fn find_by_ids(&mut self, table: &Table, ids: &[u64]) -> Result<Vec<Row>> {
+    self.select_chunked(table, table.id, ID_CHUNKS, ids)
+  }
+
+  fn find_by_ids_bulk(
+    &mut self,
+    table: &Table,
+    ids: &[u64],
+  ) -> Result<Vec<Row>> {
+    let mut results = Vec::new();
+    for ids in ids.chunks(MAX_PARAMS) {
+      results.extend(self.select_chunked(table, table.id, &[ids.len()], ids)?);
+    }
+    Ok(results)
+  }
+
+  fn find_by_hash(&mut self, table: &Table, hash: &str) -> Result<Option<Row>> {
+    let query = query::select_eq(placeholder, &self.prefix, table, table.hash);
+    Ok(self.select(table, &query, &[Value::from(hash)])?.pop())
+  }
+
+  fn find_by_hashes(
+    &mut self,
+    table: &Table,
+    hashes: &[&str],
+  ) -> Result<Vec<Row>> {
+    self.select_chunked(table, table.hash, HASH_CHUNKS, hashes)
+  }
+
+  fn find_by_column(
+    &mut self,
+    table: &Table,
+    column: &str,
+    id: u64,
+  ) -> Result<Vec<Row>> {
+    let query = query::select_eq(placeholder, &self.prefix, table, column);
+    self.select(table, &query, &[Value::from(id)])
+  }
+
+  fn find_by_column_from(
+    &mut self,
+    table: &Table,
+    column: &str,
+    id: u64,
+  ) -> Result<Vec<Row>> {
+    let query = query::select_from(placeholder, &self.prefix, table, column);
+    self.select(table, &query, &[Value::from(id)])
+  }
+
+  fn find_by_column_latest(
+    &mut self,
+    table: &Table,
+    column: &str,
+    id: u64,
+    limit: usize,
+  ) -> Result<Vec<Row>> {
+    let query =
+      query::select_eq_latest(placeholder, &self.prefix, table, column, limit);
+    self.select(table, &query, &[Value::from(id)])
+  }
+
+  fn insert(&mut self, table: &Table, row: &Row) -> Result<()> {
+    let params = row
+      .values()
+      .iter()
+      .map(|&(_, ref value)| value.clone())
+      .collect::<Vec<_>>();
+    let query = query::insert(placeholder, &self.prefix, table, row);
+    self.execute(&query, &params)
+  }
+
+  fn update(&mut self, table: &Table, row: &Row) -> Result<()> {
+    let params = query::update_params(table, row);
+    let query = query::update(placeholder, &self.prefix, table);
+    self.execute(&query, &params)
+  }
+
+  fn update_many(&mut self, table: &Table, rows: &[Row]) -> Result<()> {
+    for rows in rows.chunks(query::rows_per_statement(table, MAX_PARAMS)) {
+      let params = query::update_many_params(table, rows);
+      let query =
+        query::update_many(placeholder, &self.prefix, table, rows.len());
+      self.execute(&query, &params)?;
+    }
+    Ok(())
+  }
+
+  fn delete(&mut self, table: &Table, id: u64) -> Result<()> {
+    let query = query::delete(placeholder, &self.prefix, table);
+    self.execute(&query, &[Value::from(id)])
+  }
+
+  fn insert_event(
+    &mut self,
+    event: &str,
+    count: i32,
+    timestamp: f64,
+  ) -> Result<()> {
+    self.execute(
+      &query::insert_event(placeholder, &self.prefix),
+      &[Value::from(event), Value::from(count), Value::from(timestamp)],
+    )
+  }
+
+  fn event_counts(&mut self, since: f64) -> Result<Vec<Row>> {
+    let query = query::select_event_counts(placeholder, &self.prefix);
+    self.select(query::EVENT_COUNTS, &query, &[Value::from(since)])
+  }
+}
+
+impl ToSql for Value {
+  fn to_sql(&self, ty: &types::Type, out: &mut Vec<u8>) -> SqlResult<IsNull> {
+    match *self {
+      Value::Null => Ok(IsNull::Yes),
+      Value::Int(value) => match ty.name() {
+        "bool" => (value != 0).to_sql(ty, out),
+        "int2" => (value as i16).to_sql(ty, out),
+        "int4" => (value as i32).to_sql(ty, out),
+        "int8" => value.to_sql(ty, out),
+        "float4" => (value as f32).to_sql(ty, out),
+        "float8" => (value as f64).to_sql(ty, out),
+        _ => value.to_string().to_sql(ty, out),
+      },
+      Value::Float(value) => match ty.name() {
+        "int2" => (value as i16).to_sql(ty, out),
+        "int4" => (value as i32).to_sql(ty, out),
+        "int8" => (value as i64).to_sql(ty, out),
+        "float4" => (value as f32).to_sql(ty, out),
+        "float8" => value.to_sql(ty, out),
+        _ => value.to_string().to_sql(ty, out),
+      },
+      Value::Text(ref value) => value.to_sql(ty, out),
+    }
+  }
+
+  fn accepts(_ty: &types::Type) -> bool {
+    true
+  }
+
+  to_sql_checked!();
+}
+
+impl FromSql for Value {
+  fn from_sql(ty: &types::Type, raw: &[u8]) -> SqlResult<Self> {
+    Ok(match ty.name() {
+      "bool" => Value::from(bool::from_sql(ty, raw)?),
+      "int2" => Value::Int(i64::from(i16::from_sql(ty, raw)?)),
+      "int4" => Value::Int(i64::from(i32::from_sql(ty, raw)?)),
+      "int8" => Value::Int(i64::from_sql(ty, raw)?),
+      "float4" => Value::Float(f64::from(f32::from_sql(ty, raw)?)),
+      "float8" => Value::Float(f64::from_sql(ty, raw)?),
+      _ => Value::Text(String::from_sql(ty, raw)?),
+    })
+  }
+
+  fn from_sql_null(_ty: &types::Type) -> SqlResult<Self> {
+    Ok(Value::Null)
+  }
+
+  fn accepts(_ty: &types::Type) -> bool {
+    true
+  }
+}
+
+fn placeholder(index: usize) -> String {
+  format!("${}", index)
+}
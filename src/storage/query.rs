@@ -0,0 +1,347 @@
+use super::{Row, Table, Value};
+
+pub type Placeholder = fn(usize) -> String;
+
+pub const EVENT_COUNTS: &Table = &Table {
+  name: "txload",
+  id: "id_txload",
+  hash: "event",
+  columns: &["event", "count"],
+  update_columns: &[],
+};
+
+pub fn placeholders(placeholder: Placeholder, count: usize) -> String {
+  (1..count + 1)
+    .map(placeholder)
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+pub fn schema_version(prefix: &str) -> String {
+  format!("SELECT MAX(version) FROM {}schema_version", prefix)
+}
+
+pub fn last_id(prefix: &str, table: &Table) -> String {
+  format!(
+    "SELECT {id} FROM {}{} ORDER BY {id} DESC LIMIT 1",
+    prefix,
+    table.name,
+    id = table.id
+  )
+}
+
+pub fn select(prefix: &str, table: &Table, filter: &str) -> String {
+  format!(
+    "SELECT {} FROM {}{} WHERE {}",
+    table.columns.join(", "),
+    prefix,
+    table.name,
+    filter
+  )
+}
+
+pub fn select_all(prefix: &str, table: &Table) -> String {
+  format!(
+    "SELECT {} FROM {}{} ORDER BY {}",
+    table.columns.join(", "),
+    prefix,
+    table.name,
+    table.id
+  )
+}
+
+pub fn select_latest(prefix: &str, table: &Table, limit: usize) -> String {
+  format!(
+    "SELECT {} FROM {}{} ORDER BY {} DESC LIMIT {}",
+    table.columns.join(", "),
+    prefix,
+    table.name,
+    table.id,
+    limit
+  )
+}
+
+pub fn select_from(
+  placeholder: Placeholder,
+  prefix: &str,
+  table: &Table,
+  column: &str,
+) -> String {
+  select(prefix, table, &format!("{} >= {}", column, placeholder(1)))
+}
+
+pub fn select_eq(
+  placeholder: Placeholder,
+  prefix: &str,
+  table: &Table,
+  column: &str,
+) -> String {
+  select(prefix, table, &format!("{} = {}", column, placeholder(1)))
+}
+
+pub fn select_eq_latest(
+  placeholder: Placeholder,
+  prefix: &str,
+  table: &Table,
+  column: &str,
+  limit: usize,
+) -> String {
+  select(
+    prefix,
+    table,
+    &format!(
+      "{} = {} ORDER BY {} DESC LIMIT {}",
+      column,
+      placeholder(1),
+      table.id,
+      limit
+    ),
+  )
+}
+
+pub fn select_in(
+  placeholder: Placeholder,
+  prefix: &str,
+  table: &Table,
+  column: &str,
+  count: usize,
+) -> String {
+  select(
+    prefix,
+    table,
+    &format!("{} IN ({})", column, placeholders(placeholder, count)),
+  )
+}
+
+pub fn insert(
+  placeholder: Placeholder,
+  prefix: &str,
+  table: &Table,
+  row: &Row,
+) -> String {
+  format!(
+    "INSERT INTO {}{} ({}) VALUES ({})",
+    prefix,
+    table.name,
+    row
+      .values()
+      .iter()
+      .map(|&(column, _)| column)
+      .collect::<Vec<_>>()
+      .join(", "),
+    placeholders(placeholder, row.values().len())
+  )
+}
+
+pub fn update(placeholder: Placeholder, prefix: &str, table: &Table) -> String {
+  format!(
+    "UPDATE {}{} SET {} WHERE {} = {}",
+    prefix,
+    table.name,
+    table
+      .update_columns
+      .iter()
+      .enumerate()
+      .map(|(i, column)| format!("{} = {}", column, placeholder(i + 1)))
+      .collect::<Vec<_>>()
+      .join(", "),
+    table.id,
+    placeholder(table.update_columns.len() + 1)
+  )
+}
+
+pub fn update_many(
+  placeholder: Placeholder,
+  prefix: &str,
+  table: &Table,
+  count: usize,
+) -> String {
+  let mut index = 0;
+  let mut next = || {
+    index += 1;
+    placeholder(index)
+  };
+  let columns = table
+    .update_columns
+    .iter()
+    .map(|column| {
+      let cases = (0..count)
+        .map(|_| format!("WHEN {} THEN {}", next(), next()))
+        .collect::<Vec<_>>()
+        .join(" ");
+      format!(
+        "{column} = CASE {} {} ELSE {column} END",
+        table.id,
+        cases,
+        column = column
+      )
+    })
+    .collect::<Vec<_>>()
+    .join(", ");
+  let ids = (0..count).map(|_| next()).collect::<Vec<_>>().join(", ");
+  format!(
+    "UPDATE {}{} SET {} WHERE {} IN ({})",
+    prefix,
+    table.name,
+    columns,
+    table.id,
+    ids
+  )
+}
+
+pub fn delete(placeholder: Placeholder, prefix: &str, table: &Table) -> String {
+  format!(
+    "DELETE FROM {}{} WHERE {} = {}",
+    prefix,
+    table.name,
+    table.id,
+    placeholder(1)
+  )
+}
+
+pub fn insert_event(placeholder: Placeholder, prefix: &str) -> String {
+  format!(
+    "INSERT INTO {}txload (event, count, timestamp) VALUES ({})",
+    prefix,
+    placeholders(placeholder, 3)
+  )
+}
+
+pub fn select_event_counts(placeholder: Placeholder, prefix: &str) -> String {
+  format!(
+    "SELECT event, SUM(count) FROM {}txload WHERE timestamp >= {} \
+     GROUP BY event ORDER BY event",
+    prefix,
+    placeholder(1)
+  )
+}
+
+pub fn insert_migration(placeholder: Placeholder, prefix: &str) -> String {
+  format!(
+    "INSERT INTO {}schema_version (version, name) VALUES ({})",
+    prefix,
+    placeholders(placeholder, 2)
+  )
+}
+
+pub fn update_params(table: &Table, row: &Row) -> Vec<Value> {
+  table
+    .update_columns
+    .iter()
+    .chain(Some(&table.id))
+    .map(|column| row.get(column).cloned().unwrap_or(Value::Null))
+    .collect()
+}
+
+pub fn update_many_params(table: &Table, rows: &[Row]) -> Vec<Value> {
+  let id = |row: &Row| row.get(table.id).cloned().unwrap_or(Value::Null);
+  let mut params = Vec::new();
+  for column in table.update_columns {
+    for row in rows {
+      params.push(id(row));
+      params.push(row.get(column).cloned().unwrap_or(Value::Null));
+    }
+  }
+  params.extend(rows.iter().map(id));
+  params
+}
+
+pub fn rows_per_statement(table: &Table, max_params: usize) -> usize {
+  (max_params / (table.update_columns.len() * 2 + 1)).max(1)
+}
+
+pub fn chunks(sizes: &[usize], len: usize) -> Vec<(usize, usize)> {
+  let (mut chunks, mut offset) = (Vec::new(), 0);
+  for &size in sizes {
+    while len - offset >= size {
+      chunks.push((offset, size));
+      offset += size;
+    }
+  }
+  chunks
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const TABLE: &Table = &Table {
+    name: "tx",
+    id: "id_tx",
+    hash: "hash",
+    columns: &["id_tx", "hash", "height", "solid"],
+    update_columns: &["height", "solid"],
+  };
+
+  fn placeholder(index: usize) -> String {
+    format!("${}", index)
+  }
+
+  fn row(id: i64, height: i64, solid: i64) -> Row {
+    let mut row = Row::new();
+    row.push("id_tx", id);
+    row.push("height", height);
+    row.push("solid", solid);
+    row
+  }
+
+  #[test]
+  fn select_eq_latest_orders_by_id() {
+    assert_eq!(
+      select_eq_latest(placeholder, "p_", TABLE, "hash", 5),
+      "SELECT id_tx, hash, height, solid FROM p_tx \
+       WHERE hash = $1 ORDER BY id_tx DESC LIMIT 5"
+    );
+  }
+
+  #[test]
+  fn update_many_numbers_placeholders() {
+    assert_eq!(
+      update_many(placeholder, "p_", TABLE, 2),
+      "UPDATE p_tx SET \
+       height = CASE id_tx WHEN $1 THEN $2 WHEN $3 THEN $4 \
+       ELSE height END, \
+       solid = CASE id_tx WHEN $5 THEN $6 WHEN $7 THEN $8 \
+       ELSE solid END \
+       WHERE id_tx IN ($9, $10)"
+    );
+  }
+
+  #[test]
+  fn update_many_params_match_placeholders() {
+    let rows = [row(1, 10, 0), row(2, 20, 1)];
+    assert_eq!(
+      update_many_params(TABLE, &rows),
+      vec![
+        Value::Int(1),
+        Value::Int(10),
+        Value::Int(2),
+        Value::Int(20),
+        Value::Int(1),
+        Value::Int(0),
+        Value::Int(2),
+        Value::Int(1),
+        Value::Int(1),
+        Value::Int(2),
+      ]
+    );
+  }
+
+  #[test]
+  fn rows_per_statement_fits_params() {
+    assert_eq!(rows_per_statement(TABLE, 999), 199);
+    assert_eq!(rows_per_statement(TABLE, 10), 2);
+    assert_eq!(rows_per_statement(TABLE, 1), 1);
+  }
+
+  #[test]
+  fn chunks_cover_len() {
+    assert_eq!(
+      chunks(&[8, 4, 2, 1], 15),
+      vec![(0, 8), (8, 4), (12, 2), (14, 1)]
+    );
+    assert_eq!(chunks(&[4, 1], 9), vec![(0, 4), (4, 4), (8, 1)]);
+    assert!(chunks(&[4], 3).is_empty());
+    assert!(chunks(&[4, 1], 0).is_empty());
+  }
+}
@@ -0,0 +1,219 @@
+use super::{Error, Result};
+use std::mem;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+  Null,
+  Int(i64),
+  Float(f64),
+  Text(String),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Row {
+  values: Vec<(&'static str, Value)>,
+}
+
+pub trait FromValue: Sized {
+  fn from_value(value: Value) -> Option<Self>;
+}
+
+impl Row {
+  pub fn new() -> Self {
+    Self { values: Vec::new() }
+  }
+
+  pub fn push<T: Into<Value>>(&mut self, column: &'static str, value: T) {
+    self.values.push((column, value.into()));
+  }
+
+  pub fn get(&self, column: &str) -> Option<&Value> {
+    self
+      .values
+      .iter()
+      .find(|&&(name, _)| name == column)
+      .map(|&(_, ref value)| value)
+  }
+
+  pub fn values(&self) -> &[(&'static str, Value)] {
+    &self.values
+  }
+
+  pub fn take<T: FromValue>(&mut self, column: &str) -> Result<T> {
+    self
+      .take_value(column)
+      .ok_or_else(|| Error::ColumnNotFound(column.to_owned()))
+      .and_then(|value| {
+        T::from_value(value)
+          .ok_or_else(|| Error::InvalidValue(column.to_owned()))
+      })
+  }
+
+  pub fn take_or<T: FromValue>(
+    &mut self,
+    column: &str,
+    default: T,
+  ) -> Result<T> {
+    match self.take_value(column) {
+      None | Some(Value::Null) => Ok(default),
+      Some(value) => T::from_value(value)
+        .ok_or_else(|| Error::InvalidValue(column.to_owned())),
+    }
+  }
+
+  fn take_value(&mut self, column: &str) -> Option<Value> {
+    self
+      .values
+      .iter_mut()
+      .find(|&&mut (name, _)| name == column)
+      .map(|&mut (_, ref mut value)| mem::replace(value, Value::Null))
+  }
+}
+
+impl FromValue for Value {
+  fn from_value(value: Value) -> Option<Self> {
+    Some(value)
+  }
+}
+
+impl FromValue for i64 {
+  fn from_value(value: Value) -> Option<Self> {
+    match value {
+      Value::Int(value) => Some(value),
+      Value::Float(value) => Some(value as i64),
+      Value::Text(value) => value.trim().parse().ok(),
+      Value::Null => None,
+    }
+  }
+}
+
+impl FromValue for u64 {
+  fn from_value(value: Value) -> Option<Self> {
+    i64::from_value(value).and_then(|value| {
+      if value >= 0 {
+        Some(value as u64)
+      } else {
+        None
+      }
+    })
+  }
+}
+
+macro_rules! impl_from_value_int {
+  ($type:ty) => {
+    impl FromValue for $type {
+      fn from_value(value: Value) -> Option<Self> {
+        i64::from_value(value).and_then(|value| {
+          if value >= i64::from(<$type>::min_value()) &&
+            value <= i64::from(<$type>::max_value())
+          {
+            Some(value as $type)
+          } else {
+            None
+          }
+        })
+      }
+    }
+  };
+}
+
+impl_from_value_int!(i32);
+impl_from_value_int!(u32);
+impl_from_value_int!(u8);
+
+impl FromValue for f64 {
+  fn from_value(value: Value) -> Option<Self> {
+    match value {
+      Value::Int(value) => Some(value as f64),
+      Value::Float(value) => Some(value),
+      Value::Text(value) => value.trim().parse().ok(),
+      Value::Null => None,
+    }
+  }
+}
+
+impl FromValue for bool {
+  fn from_value(value: Value) -> Option<Self> {
+    i64::from_value(value).map(|value| value != 0)
+  }
+}
+
+impl FromValue for String {
+  fn from_value(value: Value) -> Option<Self> {
+    match value {
+      Value::Int(value) => Some(value.to_string()),
+      Value::Float(value) => Some(value.to_string()),
+      Value::Text(value) => Some(value),
+      Value::Null => None,
+    }
+  }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+  fn from_value(value: Value) -> Option<Self> {
+    match value {
+      Value::Null => Some(None),
+      value => T::from_value(value).map(Some),
+    }
+  }
+}
+
+impl From<i64> for Value {
+  fn from(value: i64) -> Value {
+    Value::Int(value)
+  }
+}
+
+impl From<u64> for Value {
+  fn from(value: u64) -> Value {
+    Value::Int(value as i64)
+  }
+}
+
+impl From<i32> for Value {
+  fn from(value: i32) -> Value {
+    Value::Int(i64::from(value))
+  }
+}
+
+impl From<u32> for Value {
+  fn from(value: u32) -> Value {
+    Value::Int(i64::from(value))
+  }
+}
+
+impl From<u8> for Value {
+  fn from(value: u8) -> Value {
+    Value::Int(i64::from(value))
+  }
+}
+
+impl From<bool> for Value {
+  fn from(value: bool) -> Value {
+    Value::Int(if value { 1 } else { 0 })
+  }
+}
+
+impl From<f64> for Value {
+  fn from(value: f64) -> Value {
+    Value::Float(value)
+  }
+}
+
+impl From<String> for Value {
+  fn from(value: String) -> Value {
+    Value::Text(value)
+  }
+}
+
+impl<'a> From<&'a str> for Value {
+  fn from(value: &'a str) -> Value {
+    Value::Text(value.to_owned())
+  }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+  fn from(value: Option<T>) -> Value {
+    value.map_or(Value::Null, Into::into)
+  }
+}
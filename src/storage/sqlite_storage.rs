@@ -0,0 +1,394 @@
+use super::{migration, query, Connection, Error, FromValue, Migration,
+            Options, Result, Row, Storage, Table, Value};
+use rusqlite;
+use rusqlite::types::{ToSql, ToSqlOutput};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const MEMORY_PATH: &str = ":memory:";
+const ID_CHUNKS: &[usize] = &[8, 4, 2, 1];
+const HASH_CHUNKS: &[usize] = &[3, 2, 1];
+const MAX_PARAMS: usize = 999;
+const MIGRATIONS: &[Migration] = &[
+  Migration {
+    version: 1,
+    name: "initial",
+    sql: include_str!("../../db/migrations/sqlite/001_initial.sql"),
+  },
+  Migration {
+    version: 2,
+    name: "node",
+    sql: include_str!("../../db/migrations/sqlite/002_node.sql"),
+  },
+  Migration {
+    version: 3,
+    name: "tx_payload",
+    sql: include_str!("../../db/migrations/sqlite/003_tx_payload.sql"),
+  },
+  Migration {
+    version: 4,
+    name: "failed_messages",
+    sql: include_str!("../../db/migrations/sqlite/004_failed_messages.sql"),
+  },
+  Migration {
+    version: 5,
+    name: "job_queue",
+    sql: include_str!("../../db/migrations/sqlite/005_job_queue.sql"),
+  },
+];
+
+pub struct SqliteStorage {
+  shared: Arc<Shared>,
+  prefix: String,
+}
+
+pub struct SqliteConnection {
+  shared: Arc<Shared>,
+  id: usize,
+  prefix: String,
+}
+
+struct Shared {
+  state: Mutex<State>,
+  released: Condvar,
+  connections: AtomicUsize,
+}
+
+struct State {
+  conn: rusqlite::Connection,
+  owner: Option<usize>,
+}
+
+impl SqliteStorage {
+  pub fn new(path: &str, options: Options) -> Result<Self> {
+    let conn = if path == MEMORY_PATH {
+      rusqlite::Connection::open_in_memory()?
+    } else {
+      rusqlite::Connection::open(path)?
+    };
+    Ok(Self {
+      shared: Arc::new(Shared {
+        state: Mutex::new(State { conn, owner: None }),
+        released: Condvar::new(),
+        connections: AtomicUsize::new(0),
+      }),
+      prefix: options.prefix,
+    })
+  }
+}
+
+impl Storage for SqliteStorage {
+  fn connect(&self) -> Box<Connection> {
+    Box::new(SqliteConnection {
+      shared: self.shared.clone(),
+      id: self.shared.connections.fetch_add(1, Ordering::SeqCst),
+      prefix: self.prefix.clone(),
+    })
+  }
+
+  fn try_connect(&self) -> Result<Box<Connection>> {
+    Ok(self.connect())
+  }
+
+  fn migrations(&self) -> &'static [Migration] {
+    MIGRATIONS
+  }
+}
+
+impl SqliteConnection {
+  fn lock(&self) -> MutexGuard<State> {
+    debug!("Mutex lock");
+    let mut state = self.shared.state.lock().unwrap();
+    while state.owner.map_or(false, |owner| owner != self.id) {
+      state = self.shared.released.wait(state).unwrap();
+    }
+    debug!("Mutex acquire");
+    state
+  }
+
+  fn release(&self, state: &mut State) {
+    state.owner = None;
+    self.shared.released.notify_all();
+  }
+
+  fn select(
+    &mut self,
+    table: &Table,
+    query: &str,
+    params: &[Value],
+  ) -> Result<Vec<Row>> {
+    let params = params.iter().map(|x| x as &ToSql).collect::<Vec<_>>();
+    let state = self.lock();
+    let mut stmt = state.conn.prepare_cached(query)?;
+    let mut rows = stmt.query(&params)?;
+    let mut results = Vec::new();
+    while let Some(row) = rows.next() {
+      let row = row?;
+      let mut result = Row::new();
+      for (i, &column) in table.columns.iter().enumerate() {
+        result.push(column, from_sqlite_value(row.get_checked(i as i32)?));
+      }
+      results.push(result);
+    }
+    Ok(results)
+  }
+
+  fn select_chunked<T>(
+    &mut self,
+    table: &Table,
+    column: &str,
+    sizes: &[usize],
+    values: &[T],
+  ) -> Result<Vec<Row>>
+  where
+    T: Clone + Into<Value>,
+  {
+    let mut results = Vec::new();
+    for (offset, size) in query::chunks(sizes, values.len()) {
+      let query =
+        query::select_in(placeholder, &self.prefix, table, column, size);
+      let params = values[offset..offset + size]
+        .iter()
+        .cloned()
+        .map(Into::into)
+        .collect::<Vec<_>>();
+      results.extend(self.select(table, &query, &params)?);
+    }
+    Ok(results)
+  }
+
+  fn execute(&mut self, query: &str, params: &[Value]) -> Result<()> {
+    let params = params.iter().map(|x| x as &ToSql).collect::<Vec<_>>();
+    let state = self.lock();
+    state.conn.prepare_cached(query)?.execute(&params)?;
+    Ok(())
+  }
+
+  fn scalar(&mut self, query: &str) -> Result<Option<Value>> {
+    let state = self.lock();
+    let mut stmt = state.conn.prepare_cached(query)?;
+    let mut rows = stmt.query(&[])?;
+    let value = match rows.next() {
+      Some(row) => Some(from_sqlite_value(row?.get_checked(0)?)),
+      None => None,
+    };
+    Ok(value)
+  }
+}
+
+impl Connection for SqliteConnection {
+  fn schema_version(&mut self) -> Result<u32> {
+    match self.scalar(&query::schema_version(&self.prefix)) {
+      Ok(value) => migration::parse_version(value),
+      Err(ref err) if err.is_missing_table() => Ok(0),
+      Err(err) => Err(err),
+    }
+  }
+
+  fn apply_migration(&mut self, migration: &Migration) -> Result<()> {
+    let version = Value::from(migration.version);
+    let name = Value::from(migration.name);
+    let mut state = self.lock();
+    let transaction = state.conn.transaction()?;
+    transaction.execute_batch(&migration.render(&self.prefix))?;
+    transaction.execute(
+      &query::insert_migration(placeholder, &self.prefix),
+      &[&version as &ToSql, &name],
+    )?;
+    transaction.commit()?;
+    Ok(())
+  }
+
+  fn begin(&mut self) -> Result<()> {
+    let mut state = self.lock();
+    state.conn.execute_batch("BEGIN")?;
+    state.owner = Some(self.id);
+    Ok(())
+  }
+
+  fn commit(&mut self) -> Result<()> {
+    let mut state = self.lock();
+    state.conn.execute_batch("COMMIT")?;
+    self.release(&mut state);
+    Ok(())
+  }
+
+  fn rollback(&mut self) -> Result<()> {
+    let mut state = self.lock();
+    let result = state.conn.execute_batch("ROLLBACK");
+    self.release(&mut state);
+    result?;
+    Ok(())
+  }
+
+  fn last_id(&mut self, table: &Table) -> Result<Option<u64>> {
+    match self.scalar(&query::last_id(&self.prefix, table))? {
+      Some(value) => u64::from_value(value)
+        .map(Some)
+        .ok_or_else(|| Error::InvalidValue(table.id.to_owned())),
+      None => Ok(None),
+    }
+  }
+
+  fn find_all(&mut self, table: &Table) -> Result<Vec<Row>> {
+    let query = query::select_all(&self.prefix, table);
+    self.select(table, &query, &[])
+  }
+
+  fn find_latest(&mut self, table: &Table, limit: usize) -> Result<Vec<Row>> {
+    let query = query::select_latest(&self.prefix, table, limit);
+    self.select(table, &query, &[])
+  }
+
+  fn find_by_id(&mut self, table: &Table, id: u64) -> Result<Option<Row>> {
+    let query = query::select_eq(placeholder, &self.prefix, table, table.id);
+    Ok(self.select(table, &query, &[Value::from(id)])?.pop())
+  }
+
+  fn find_by_ids(&mut self, table: &Table, ids: &[u64]) -> Result<Vec<Row>> {
+    self.select_chunked(table, table.id, ID_CHUNKS, ids)
+  }
+
+  fn find_by_ids_bulk(
+    &mut self,
+    table: &Table,
+    ids: &[u64],
+  ) -> Result<Vec<Row>> {
+    let mut results = Vec::new();
+    for ids in ids.chunks(MAX_PARAMS) {
+      results.extend(self.select_chunked(table, table.id, &[ids.len()], ids)?);
+    }
+    Ok(results)
+  }
+
+  fn find_by_hash(&mut self, table: &Table, hash: &str) -> Result<Option<Row>> {
+    let query = query::select_eq(placeholder, &self.prefix, table, table.hash);
+    Ok(self.select(table, &query, &[Value::from(hash)])?.pop())
+  }
+
+  fn find_by_hashes(
+    &mut self,
+    table: &Table,
+    hashes: &[&str],
+  ) -> Result<Vec<Row>> {
+    self.select_chunked(table, table.hash, HASH_CHUNKS, hashes)
+  }
+
+  fn find_by_column(
+    &mut self,
+    table: &Table,
+    column: &str,
+    id: u64,
+  ) -> Result<Vec<Row>> {
+    let query = query::select_eq(placeholder, &self.prefix, table, column);
+    self.select(table, &query, &[Value::from(id)])
+  }
+
+  fn find_by_column_from(
+    &mut self,
+    table: &Table,
+    column: &str,
+    id: u64,
+  ) -> Result<Vec<Row>> {
+    let query = query::select_from(placeholder, &self.prefix, table, column);
+    self.select(table, &query, &[Value::from(id)])
+  }
+
+  fn find_by_column_latest(
+    &mut self,
+    table: &Table,
+    column: &str,
+    id: u64,
+    limit: usize,
+  ) -> Result<Vec<Row>> {
+    let query =
+      query::select_eq_latest(placeholder, &self.prefix, table, column, limit);
+    self.select(table, &query, &[Value::from(id)])
+  }
+
+  fn insert(&mut self, table: &Table, row: &Row) -> Result<()> {
+    let params = row
+      .values()
+      .iter()
+      .map(|&(_, ref value)| value.clone())
+      .collect::<Vec<_>>();
+    let query = query::insert(placeholder, &self.prefix, table, row);
+    self.execute(&query, &params)
+  }
+
+  fn update(&mut self, table: &Table, row: &Row) -> Result<()> {
+    let params = query::update_params(table, row);
+    let query = query::update(placeholder, &self.prefix, table);
+    self.execute(&query, &params)
+  }
+
+  fn update_many(&mut self, table: &Table, rows: &[Row]) -> Result<()> {
+    for rows in rows.chunks(query::rows_per_statement(table, MAX_PARAMS)) {
+      let params = query::update_many_params(table, rows);
+      let query =
+        query::update_many(placeholder, &self.prefix, table, rows.len());
+      self.execute(&query, &params)?;
+    }
+    Ok(())
+  }
+
+  fn delete(&mut self, table: &Table, id: u64) -> Result<()> {
+    let query = query::delete(placeholder, &self.prefix, table);
+    self.execute(&query, &[Value::from(id)])
+  }
+
+  fn insert_event(
+    &mut self,
+    event: &str,
+    count: i32,
+    timestamp: f64,
+  ) -> Result<()> {
+    self.execute(
+      &query::insert_event(placeholder, &self.prefix),
+      &[Value::from(event), Value::from(count), Value::from(timestamp)],
+    )
+  }
+
+  fn event_counts(&mut self, since: f64) -> Result<Vec<Row>> {
+    let query = query::select_event_counts(placeholder, &self.prefix);
+    self.select(query::EVENT_COUNTS, &query, &[Value::from(since)])
+  }
+}
+
+impl Drop for SqliteConnection {
+  fn drop(&mut self) {
+    if let Ok(mut state) = self.shared.state.lock() {
+      if state.owner == Some(self.id) {
+        state.conn.execute_batch("ROLLBACK").ok();
+        self.release(&mut state);
+      }
+    }
+  }
+}
+
+impl ToSql for Value {
+  fn to_sql(&self) -> rusqlite::Result<ToSqlOutput> {
+    Ok(ToSqlOutput::Owned(match *self {
+      Value::Null => rusqlite::types::Value::Null,
+      Value::Int(value) => rusqlite::types::Value::Integer(value),
+      Value::Float(value) => rusqlite::types::Value::Real(value),
+      Value::Text(ref value) => rusqlite::types::Value::Text(value.clone()),
+    }))
+  }
+}
+
+fn from_sqlite_value(value: rusqlite::types::Value) -> Value {
+  match value {
+    rusqlite::types::Value::Null => Value::Null,
+    rusqlite::types::Value::Integer(value) => Value::Int(value),
+    rusqlite::types::Value::Real(value) => Value::Float(value),
+    rusqlite::types::Value::Text(value) => Value::Text(value),
+    rusqlite::types::Value::Blob(value) => {
+      Value::Text(String::from_utf8_lossy(&value).into_owned())
+    }
+  }
+}
+
+fn placeholder(_index: usize) -> String {
+  String::from("?")
+}
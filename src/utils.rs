@@ -2,6 +2,7 @@ use iota_kerl;
 use iota_sign;
 use iota_trytes;
 use mysql;
+use postgres::{self, TlsMode};
 use std::thread;
 use std::time::{Duration, SystemTime, SystemTimeError, UNIX_EPOCH};
 
@@ -13,8 +14,16 @@ pub trait DurationUtils {
   fn as_milliseconds(&self) -> f64;
 }
 
-pub trait MysqlConnUtils {
-  fn new_retry(uri: &str, retry_interval: u64) -> Self;
+pub trait MysqlConnUtils: Sized {
+  fn new_once(uri: &str, database: &str) -> Result<Self, mysql::Error>;
+
+  fn new_retry(uri: &str, database: &str, retry_interval: u64) -> Self;
+}
+
+pub trait PostgresConnUtils: Sized {
+  fn new_once(uri: &str, database: &str) -> Result<Self, postgres::Error>;
+
+  fn new_retry(uri: &str, database: &str, retry_interval: u64) -> Self;
 }
 
 impl SystemTimeUtils for SystemTime {
@@ -31,17 +40,23 @@ impl DurationUtils for Duration {
 }
 
 impl MysqlConnUtils for mysql::Conn {
-  fn new_retry(uri: &str, retry_interval: u64) -> Self {
-    const CREATE_DB: &str = include_str!("../db/create-db.sql");
+  fn new_once(uri: &str, database: &str) -> Result<Self, mysql::Error> {
+    mysql::Conn::new(&format!("{}/{}?prefer_socket=false", uri, database))
+  }
+
+  fn new_retry(uri: &str, database: &str, retry_interval: u64) -> Self {
+    let create_db = format!(
+      "CREATE DATABASE IF NOT EXISTS {} DEFAULT CHARACTER SET utf8",
+      database
+    );
     let retry_interval = Duration::from_millis(retry_interval);
     let root_uri = format!("{}/?prefer_socket=false", uri);
-    let iota_uri = format!("{}/iota?prefer_socket=false", uri);
     loop {
-      match mysql::Conn::new(&iota_uri) {
+      match Self::new_once(uri, database) {
         Ok(conn) => return conn,
         Err(mysql::Error::MySqlError(ref err)) if err.code == 1049 => {
           if let Ok(mut conn) = mysql::Conn::new(&root_uri) {
-            if let Err(err) = conn.query(CREATE_DB) {
+            if let Err(err) = conn.query(&create_db) {
               warn!("MySQL create db failure: {}. Retrying...", err);
               thread::sleep(retry_interval);
             }
@@ -56,12 +71,76 @@ impl MysqlConnUtils for mysql::Conn {
   }
 }
 
+impl PostgresConnUtils for postgres::Connection {
+  fn new_once(uri: &str, database: &str) -> Result<Self, postgres::Error> {
+    postgres::Connection::connect(
+      format!("{}/{}", uri, database).as_str(),
+      TlsMode::None,
+    )
+  }
+
+  fn new_retry(uri: &str, database: &str, retry_interval: u64) -> Self {
+    let create_db = format!("CREATE DATABASE {}", database);
+    let retry_interval = Duration::from_millis(retry_interval);
+    let root_uri = format!("{}/postgres", uri);
+    let connect = |uri: &str| postgres::Connection::connect(uri, TlsMode::None);
+    loop {
+      match Self::new_once(uri, database) {
+        Ok(conn) => return conn,
+        Err(ref err)
+          if err.code().map_or(false, |code| code.code() == "3D000") =>
+        {
+          let result = connect(&root_uri)
+            .and_then(|conn| conn.batch_execute(&create_db));
+          if let Err(err) = result {
+            warn!("PostgreSQL create db failure: {}. Retrying...", err);
+            thread::sleep(retry_interval);
+          }
+        }
+        Err(err) => {
+          warn!("PostgreSQL connection failure: {}. Retrying...", err);
+          thread::sleep(retry_interval);
+        }
+      }
+    }
+  }
+}
+
 pub fn trits_string(number: isize, length: usize) -> Option<String> {
   let mut trits = vec![0; length * iota_trytes::TRITS_PER_TRYTE];
   iota_trytes::num::int2trits(number, &mut trits);
   iota_trytes::trits_to_string(&trits)
 }
 
+pub fn trits_to_int(trits: &[i8]) -> i64 {
+  trits
+    .iter()
+    .rev()
+    .fold(0, |number, &trit| number * 3 + i64::from(trit))
+}
+
+pub fn is_tryte(c: char) -> bool {
+  c == '9' || (c >= 'A' && c <= 'Z')
+}
+
+pub fn json_string(string: &str) -> String {
+  let mut out = String::with_capacity(string.len() + 2);
+  out.push('"');
+  for c in string.chars() {
+    match c {
+      '"' => out.push_str("\\\""),
+      '\\' => out.push_str("\\\\"),
+      '\n' => out.push_str("\\n"),
+      '\r' => out.push_str("\\r"),
+      '\t' => out.push_str("\\t"),
+      c if c < ' ' => out.push_str(&format!("\\u{:04x}", c as u32)),
+      c => out.push(c),
+    }
+  }
+  out.push('"');
+  out
+}
+
 pub fn trits_checksum(source: &str) -> Option<String> {
   let mut checksum = [0; iota_sign::CHECKSUM_LEN];
   let mut kerl = iota_kerl::Kerl::default();
@@ -1,34 +1,37 @@
 use super::Result;
 use event;
+use job_queue::{Durable, JobStore, Pending};
 use mapper::{BundleMapper, Mapper, Record, TransactionMapper,
              TransactionRecord};
-use mysql;
+use metrics::{Metrics, Stage};
 use std::collections::{HashSet, VecDeque};
-use std::sync::{mpsc, Arc, Mutex};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Instant, SystemTime};
-use utils::{DurationUtils, MysqlConnUtils, SystemTimeUtils};
+use storage::{Connection, Storage};
+use utils::{DurationUtils, SystemTimeUtils};
+use worker::Queue;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum ApproveJob {
   Reverse(ReverseApproveJob),
   Front(FrontApproveJob),
   Milestone(MilestoneApproveJob),
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct ReverseApproveJob {
   id: u64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct FrontApproveJob {
   id_trunk: u64,
   id_branch: u64,
   mst_timestamp: f64,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct MilestoneApproveJob {
   id_bundle: u64,
   id_trunk: u64,
@@ -36,44 +39,50 @@ pub struct MilestoneApproveJob {
   mst_timestamp: f64,
 }
 
-pub struct ApproveThread<'a> {
-  pub approve_rx: mpsc::Receiver<ApproveJob>,
-  pub mysql_uri: &'a str,
-  pub retry_interval: u64,
+#[derive(Clone)]
+pub struct ApproveThread {
+  pub approve_queue: Queue<Pending<ApproveJob>>,
+  pub storage: Arc<Storage>,
+  pub job_store: Arc<JobStore>,
+  pub metrics: Arc<Metrics>,
   pub transaction_mapper: Arc<TransactionMapper>,
   pub bundle_mapper: Arc<BundleMapper>,
 }
 
-impl<'a> ApproveThread<'a> {
-  pub fn spawn(self) {
+impl ApproveThread {
+  pub fn spawn(self) -> thread::JoinHandle<()> {
     let Self {
-      approve_rx,
-      mysql_uri,
-      retry_interval,
+      approve_queue,
+      storage,
+      job_store,
+      metrics,
       transaction_mapper,
       bundle_mapper,
     } = self;
-    let mut conn = mysql::Conn::new_retry(mysql_uri, retry_interval);
+    let mut conn = storage.connect();
     let thread = thread::Builder::new().name("approve".into());
     let thread = thread.spawn(move || {
       let transaction_mapper = &*transaction_mapper;
       let bundle_mapper = &*bundle_mapper;
-      loop {
-        let job = approve_rx.recv().expect("Thread communication failure");
+      while let Some(pending) = approve_queue.pop() {
+        let job = &pending.job;
         let duration = Instant::now();
-        let result = job.perform(&mut conn, transaction_mapper, bundle_mapper);
+        let result = job.perform(&mut *conn, transaction_mapper, bundle_mapper);
         let duration = duration.elapsed().as_milliseconds();
+        metrics.observe(Stage::Approve, duration);
         match result {
           Ok(()) => {
             info!("{:.3}ms {:?}", duration, job);
+            job_store.ack(&pending);
           }
           Err(err) => {
+            metrics.failure(Stage::Approve);
             error!("{:.3}ms {}", duration, err);
           }
         }
       }
     });
-    thread.expect("Thread spawn failure");
+    thread.expect("Thread spawn failure")
   }
 }
 
@@ -100,9 +109,9 @@ impl ApproveJob {
     ))
   }
 
-  fn perform(
+  pub fn perform(
     &self,
-    conn: &mut mysql::Conn,
+    conn: &mut Connection,
     transaction_mapper: &TransactionMapper,
     bundle_mapper: &BundleMapper,
   ) -> Result<()> {
@@ -118,6 +127,48 @@ impl ApproveJob {
   }
 }
 
+impl Durable for ApproveJob {
+  const KIND: &'static str = "approve";
+
+  fn encode(&self) -> String {
+    match *self {
+      ApproveJob::Reverse(ref reverse) => format!("reverse {}", reverse.id),
+      ApproveJob::Front(ref front) => format!(
+        "front {} {} {}",
+        front.id_trunk,
+        front.id_branch,
+        front.mst_timestamp
+      ),
+      ApproveJob::Milestone(ref milestone) => format!(
+        "milestone {} {} {} {}",
+        milestone.id_bundle,
+        milestone.id_trunk,
+        milestone.id_branch,
+        milestone.mst_timestamp
+      ),
+    }
+  }
+
+  fn decode(data: &str) -> Option<Self> {
+    let mut fields = data.split(' ');
+    match fields.next()? {
+      "reverse" => Some(ApproveJob::reverse(fields.next()?.parse().ok()?)),
+      "front" => Some(ApproveJob::front(
+        fields.next()?.parse().ok()?,
+        fields.next()?.parse().ok()?,
+        fields.next()?.parse().ok()?,
+      )),
+      "milestone" => Some(ApproveJob::milestone(
+        fields.next()?.parse().ok()?,
+        fields.next()?.parse().ok()?,
+        fields.next()?.parse().ok()?,
+        fields.next()?.parse().ok()?,
+      )),
+      _ => None,
+    }
+  }
+}
+
 impl ReverseApproveJob {
   fn new(id: u64) -> Self {
     Self { id }
@@ -125,7 +176,7 @@ impl ReverseApproveJob {
 
   fn perform(
     &self,
-    conn: &mut mysql::Conn,
+    conn: &mut Connection,
     transaction_mapper: &TransactionMapper,
   ) -> Result<()> {
     let mut mst_timestamp = None;
@@ -169,7 +220,7 @@ impl FrontApproveJob {
 
   fn perform(
     &self,
-    conn: &mut mysql::Conn,
+    conn: &mut Connection,
     transaction_mapper: &TransactionMapper,
   ) -> Result<()> {
     let (timestamp, mut counter) = (SystemTime::milliseconds_since_epoch()?, 0);
@@ -222,7 +273,7 @@ impl MilestoneApproveJob {
 
   fn perform(
     &self,
-    conn: &mut mysql::Conn,
+    conn: &mut Connection,
     transaction_mapper: &TransactionMapper,
     bundle_mapper: &BundleMapper,
   ) -> Result<()> {
@@ -275,3 +326,40 @@ fn approve(
   transaction.set_mst_a(true);
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn round_trips_jobs() {
+    let jobs = vec![
+      ApproveJob::reverse(7),
+      ApproveJob::front(1, 2, 1_500_000_000.25),
+      ApproveJob::milestone(3, 4, 5, 1_500_000_001.5),
+    ];
+    for job in jobs {
+      assert_eq!(ApproveJob::decode(&job.encode()), Some(job));
+    }
+  }
+
+  #[test]
+  fn encodes_fields_in_order() {
+    assert_eq!(ApproveJob::reverse(7).encode(), "reverse 7");
+    assert_eq!(ApproveJob::front(1, 2, 3.5).encode(), "front 1 2 3.5");
+    assert_eq!(
+      ApproveJob::milestone(3, 4, 5, 6.0).encode(),
+      "milestone 3 4 5 6"
+    );
+  }
+
+  #[test]
+  fn rejects_malformed_jobs() {
+    assert_eq!(ApproveJob::decode(""), None);
+    assert_eq!(ApproveJob::decode("reverse"), None);
+    assert_eq!(ApproveJob::decode("reverse x"), None);
+    assert_eq!(ApproveJob::decode("front 1 2"), None);
+    assert_eq!(ApproveJob::decode("milestone 1 2 3"), None);
+    assert_eq!(ApproveJob::decode("unknown 1"), None);
+  }
+}
@@ -1,68 +1,67 @@
-use super::Result;
+use super::{Queue, Result};
+use job_queue::{Durable, JobStore, Pending};
 use mapper::{Index, Mapper, TransactionMapper};
-use mysql;
+use metrics::{Metrics, Stage};
 use std::collections::{HashSet, VecDeque};
-use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::sync::{Arc, MutexGuard};
 use std::thread;
 use std::time::Instant;
-use utils::{DurationUtils, MysqlConnUtils};
+use storage::{Connection, Storage};
+use utils::DurationUtils;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct CalculateJob {
   pivot_id: u64,
 }
 
-pub struct CalculateThreads<'a> {
-  pub calculate_rx: mpsc::Receiver<CalculateJob>,
-  pub mysql_uri: &'a str,
-  pub retry_interval: u64,
+#[derive(Clone)]
+pub struct CalculateThreads {
+  pub calculate_queue: Queue<Pending<CalculateJob>>,
+  pub storage: Arc<Storage>,
+  pub job_store: Arc<JobStore>,
+  pub metrics: Arc<Metrics>,
   pub calculation_threads: usize,
   pub calculation_limit: usize,
   pub transaction_mapper: Arc<TransactionMapper>,
 }
 
-impl<'a> CalculateThreads<'a> {
-  pub fn spawn(self) {
-    let Self {
-      calculate_rx,
-      mysql_uri,
-      retry_interval,
-      calculation_threads,
-      calculation_limit,
-      transaction_mapper,
-    } = self;
-    let calculate_rx = Arc::new(Mutex::new(calculate_rx));
-    for i in 0..calculation_threads {
-      let mut conn = mysql::Conn::new_retry(mysql_uri, retry_interval);
-      let transaction_mapper = transaction_mapper.clone();
-      let calculate_rx = calculate_rx.clone();
-      let thread = thread::Builder::new().name(format!("calculate#{}", i));
-      let thread = thread.spawn(move || {
-        let transaction_mapper = &*transaction_mapper;
-        let calculate_rx = &*calculate_rx;
-        loop {
-          let job = {
-            debug!("Mutex lock");
-            let rx = calculate_rx.lock().unwrap();
-            debug!("Mutex acquire");
-            rx.recv().expect("Thread communication failure")
-          };
-          let duration = Instant::now();
-          let result =
-            job.perform(&mut conn, transaction_mapper, calculation_limit);
-          let duration = duration.elapsed().as_milliseconds();
-          match result {
-            Ok(()) => {
-              info!("#{} {:.3}ms {:?}", i, duration, job);
-            }
-            Err(err) => {
-              error!("#{} {:.3}ms {}", i, duration, err);
-            }
+impl CalculateThreads {
+  pub fn spawn(self) -> Vec<thread::JoinHandle<()>> {
+    (0..self.calculation_threads)
+      .map(|i| self.spawn_thread(i))
+      .collect()
+  }
+
+  pub fn spawn_thread(&self, i: usize) -> thread::JoinHandle<()> {
+    let calculate_queue = self.calculate_queue.clone();
+    let calculation_limit = self.calculation_limit;
+    let transaction_mapper = self.transaction_mapper.clone();
+    let job_store = self.job_store.clone();
+    let metrics = self.metrics.clone();
+    let mut conn = self.storage.connect();
+    let thread = thread::Builder::new().name(format!("calculate#{}", i));
+    let thread = thread.spawn(move || {
+      let transaction_mapper = &*transaction_mapper;
+      while let Some(pending) = calculate_queue.pop() {
+        let job = &pending.job;
+        let duration = Instant::now();
+        let result =
+          job.perform(&mut *conn, transaction_mapper, calculation_limit);
+        let duration = duration.elapsed().as_milliseconds();
+        metrics.observe(Stage::Calculate, duration);
+        match result {
+          Ok(()) => {
+            info!("#{} {:.3}ms {:?}", i, duration, job);
+            job_store.ack(&pending);
+          }
+          Err(err) => {
+            metrics.failure(Stage::Calculate);
+            error!("#{} {:.3}ms {}", i, duration, err);
           }
         }
-      });
-      thread.expect("Thread spawn failure");
-    }
+      }
+    });
+    thread.expect("Thread spawn failure")
   }
 }
 
@@ -73,7 +72,7 @@ impl CalculateJob {
 
   pub fn perform(
     &self,
-    conn: &mut mysql::Conn,
+    conn: &mut Connection,
     transaction_mapper: &TransactionMapper,
     calculation_limit: usize,
   ) -> Result<()> {
@@ -89,8 +88,20 @@ impl CalculateJob {
   }
 }
 
+impl Durable for CalculateJob {
+  const KIND: &'static str = "calculate";
+
+  fn encode(&self) -> String {
+    self.pivot_id.to_string()
+  }
+
+  fn decode(data: &str) -> Option<Self> {
+    data.parse().ok().map(Self::new)
+  }
+}
+
 fn calculate_front(
-  conn: &mut mysql::Conn,
+  conn: &mut Connection,
   transaction_mapper: &TransactionMapper,
   pivot_id: u64,
 ) -> Result<f64> {
@@ -141,7 +152,7 @@ fn calculate_front_refs(
 }
 
 fn calculate_back(
-  conn: &mut mysql::Conn,
+  conn: &mut Connection,
   transaction_mapper: &TransactionMapper,
   calculation_limit: usize,
   pivot_id: u64,
@@ -173,3 +184,22 @@ fn calculate_back(
   }
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn round_trips_jobs() {
+    let job = CalculateJob::new(42);
+    assert_eq!(job.encode(), "42");
+    assert_eq!(CalculateJob::decode(&job.encode()), Some(job));
+  }
+
+  #[test]
+  fn rejects_malformed_jobs() {
+    assert_eq!(CalculateJob::decode(""), None);
+    assert_eq!(CalculateJob::decode("x"), None);
+    assert_eq!(CalculateJob::decode("-1"), None);
+  }
+}
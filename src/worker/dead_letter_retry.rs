@@ -0,0 +1,95 @@
+use super::{Error, Result};
+use dead_letter::{DeadLetter, Stage};
+use mapper::{AddressMapper, BundleMapper, TransactionMapper};
+use message::TransactionMessage;
+use std::time::SystemTime;
+use storage::Connection;
+use utils::{self, SystemTimeUtils};
+
+const HASH_SIZE: usize = 81;
+
+pub struct DeadLetterRetry<'a> {
+  pub transaction_mapper: &'a TransactionMapper,
+  pub address_mapper: &'a AddressMapper,
+  pub bundle_mapper: &'a BundleMapper,
+  pub milestone_address: &'a str,
+  pub milestone_start_index: &'a str,
+  pub calculation_limit: usize,
+}
+
+impl<'a> DeadLetterRetry<'a> {
+  pub fn perform(&self, conn: &mut Connection) -> Result<(Vec<u64>, usize)> {
+    let null_hash = utils::trits_string(0, HASH_SIZE)
+      .expect("Can't convert null_hash to trits");
+    let (mut retried, mut failed) = (Vec::new(), 0);
+    for dead_letter in DeadLetter::find_all(conn)? {
+      match self.retry(conn, &dead_letter.message, &null_hash) {
+        Ok(()) => {
+          info!("Dead letter #{} retried", dead_letter.id);
+          retried.push(dead_letter.id);
+        }
+        Err((stage, err)) => {
+          warn!("Dead letter #{} failure: {}", dead_letter.id, err);
+          let timestamp = SystemTime::milliseconds_since_epoch()?;
+          dead_letter.update(conn, stage, &err.to_string(), timestamp)?;
+          failed += 1;
+        }
+      }
+    }
+    Ok((retried, failed))
+  }
+
+  fn retry(
+    &self,
+    conn: &mut Connection,
+    message: &str,
+    null_hash: &str,
+  ) -> ::std::result::Result<(), (Stage, Error)> {
+    let message = TransactionMessage::parse_topic(
+      message,
+      self.milestone_address,
+      self.milestone_start_index,
+    ).map_err(|err| (Stage::Parse, err.into()))?;
+    self
+      .process(conn, &message, null_hash)
+      .map_err(|err| (Stage::Process, err))
+  }
+
+  fn process(
+    &self,
+    conn: &mut Connection,
+    message: &TransactionMessage,
+    null_hash: &str,
+  ) -> Result<()> {
+    let jobs = message.perform(
+      conn,
+      self.transaction_mapper,
+      self.address_mapper,
+      self.bundle_mapper,
+      null_hash,
+      None,
+    )?;
+    let (approve_data, solidate_data, calculate_data) = match jobs {
+      (None, None, None) => message.derive_jobs(conn, self.transaction_mapper)?,
+      (approve_data, solidate_data, calculate_data) => (
+        approve_data.map(|pending| pending.job),
+        solidate_data.map(|pending| pending.job),
+        calculate_data.map(|pending| pending.job),
+      ),
+    };
+    if let Some(approve_data) = approve_data {
+      approve_data.perform(conn, self.transaction_mapper, self.bundle_mapper)?;
+    }
+    if let Some(solidate_data) = solidate_data {
+      solidate_data.perform(conn, self.transaction_mapper)?;
+    }
+    if let Some(calculate_data) = calculate_data {
+      calculate_data.perform(
+        conn,
+        self.transaction_mapper,
+        self.calculation_limit,
+      )?;
+    }
+    Ok(())
+  }
+}
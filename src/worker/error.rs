@@ -1,14 +1,14 @@
 use mapper;
 use message;
-use mysql;
 use std::{error, fmt, result, time};
+use storage;
 
 #[derive(Debug)]
 pub enum Error {
   Message(message::Error),
   Mapper(mapper::Error),
   SystemTime(time::SystemTimeError),
-  Mysql(mysql::Error),
+  Storage(storage::Error),
 }
 
 pub type Result<T> = result::Result<T, Error>;
@@ -19,7 +19,7 @@ impl fmt::Display for Error {
       Error::Message(ref err) => write!(f, "Message error: {}", err),
       Error::Mapper(ref err) => write!(f, "Mapper error: {}", err),
       Error::SystemTime(ref err) => write!(f, "SystemTime error: {}", err),
-      Error::Mysql(ref err) => write!(f, "MySQL error: {}", err),
+      Error::Storage(ref err) => write!(f, "Storage error: {}", err),
     }
   }
 }
@@ -30,7 +30,7 @@ impl error::Error for Error {
       Error::Message(ref err) => err.description(),
       Error::Mapper(ref err) => err.description(),
       Error::SystemTime(ref err) => err.description(),
-      Error::Mysql(ref err) => err.description(),
+      Error::Storage(ref err) => err.description(),
     }
   }
 
@@ -39,7 +39,7 @@ impl error::Error for Error {
       Error::Message(ref err) => Some(err),
       Error::Mapper(ref err) => Some(err),
       Error::SystemTime(ref err) => Some(err),
-      Error::Mysql(ref err) => Some(err),
+      Error::Storage(ref err) => Some(err),
     }
   }
 }
@@ -62,8 +62,8 @@ impl From<time::SystemTimeError> for Error {
   }
 }
 
-impl From<mysql::Error> for Error {
-  fn from(err: mysql::Error) -> Error {
-    Error::Mysql(err)
+impl From<storage::Error> for Error {
+  fn from(err: storage::Error) -> Error {
+    Error::Storage(err)
   }
 }
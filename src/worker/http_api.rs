@@ -0,0 +1,364 @@
+use super::Result;
+use event;
+use mapper::{self, AddressMapper, BundleMapper, Mapper, Record,
+             TransactionMapper, TransactionRecord};
+use std::str::FromStr;
+use std::time::SystemTime;
+use storage::Connection;
+use utils::{self, json_string, SystemTimeUtils};
+
+const HASH_SIZE: usize = 81;
+const ADDRESS_SIZE: usize = 90;
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+const DEFAULT_TXLOAD_PERIOD: f64 = 3_600_000.0;
+
+pub struct HttpApi<'a> {
+  pub transaction_mapper: &'a TransactionMapper,
+  pub address_mapper: &'a AddressMapper,
+  pub bundle_mapper: &'a BundleMapper,
+}
+
+impl<'a> HttpApi<'a> {
+  pub fn perform(
+    &self,
+    conn: &mut Connection,
+    path: &str,
+    query: &str,
+  ) -> Result<Option<String>> {
+    let mut segments = path.trim_matches('/').split('/');
+    if segments.next() != Some("api") {
+      return Ok(None);
+    }
+    match (
+      segments.next(),
+      segments.next(),
+      segments.next(),
+      segments.next(),
+    ) {
+      (Some("transactions"), Some(hash), None, None) => {
+        self.transaction(conn, hash)
+      }
+      (Some("transactions"), Some(hash), Some("approvers"), None) => {
+        self.approvers(conn, hash)
+      }
+      (Some("transactions"), Some(hash), Some("status"), None) => {
+        self.status(conn, hash)
+      }
+      (Some("addresses"), Some(address), Some("transactions"), None) => {
+        let limit = param(query, "limit").unwrap_or(DEFAULT_LIMIT);
+        self.address(conn, address, limit.min(MAX_LIMIT))
+      }
+      (Some("bundles"), Some(bundle), None, None) => self.bundle(conn, bundle),
+      (Some("txload"), None, None, None) => {
+        self.txload(conn, param(query, "since"))
+      }
+      _ => Ok(None),
+    }
+  }
+
+  fn transaction(
+    &self,
+    conn: &mut Connection,
+    hash: &str,
+  ) -> Result<Option<String>> {
+    let record = match self.find_transaction(conn, hash)? {
+      Some(record) => record,
+      None => return Ok(None),
+    };
+    let trunk = hash_of(self.transaction_mapper, conn, record.id_trunk())?;
+    let branch = hash_of(self.transaction_mapper, conn, record.id_branch())?;
+    let address = hash_of(self.address_mapper, conn, record.id_address())?;
+    let bundle = hash_of(self.bundle_mapper, conn, record.id_bundle())?;
+    Ok(Some(format!(
+      "{{{},\"arrival\":{},\"direct_approvers\":{},\"height\":{},\
+       \"weight\":{},\"trunk\":{},\"branch\":{},\"address\":{},\
+       \"bundle\":{}}}",
+      fields(&record),
+      record.arrival(),
+      record.da(),
+      record.height(),
+      record.weight(),
+      json_option(trunk),
+      json_option(branch),
+      json_option(address),
+      json_option(bundle)
+    )))
+  }
+
+  fn approvers(
+    &self,
+    conn: &mut Connection,
+    hash: &str,
+  ) -> Result<Option<String>> {
+    let id = match self.find_transaction(conn, hash)? {
+      Some(record) => record.id(),
+      None => return Ok(None),
+    };
+    let trunk = hashes(&self.transaction_mapper.peek_trunk(conn, id)?);
+    let branch = hashes(&self.transaction_mapper.peek_branch(conn, id)?);
+    Ok(Some(format!(
+      "{{\"hash\":{},\"trunk\":[{}],\"branch\":[{}]}}",
+      json_string(hash),
+      trunk.join(","),
+      branch.join(",")
+    )))
+  }
+
+  fn status(
+    &self,
+    conn: &mut Connection,
+    hash: &str,
+  ) -> Result<Option<String>> {
+    let record = match self.find_transaction(conn, hash)? {
+      Some(record) => record,
+      None => return Ok(None),
+    };
+    Ok(Some(format!(
+      "{{\"hash\":{},\"milestone\":{},\"confirmed\":{},\"conftime\":{},\
+       \"solid\":{}}}",
+      json_string(record.hash()),
+      record.is_mst(),
+      record.mst_a(),
+      record.conftime(),
+      record.solid().is_complete()
+    )))
+  }
+
+  fn address(
+    &self,
+    conn: &mut Connection,
+    address: &str,
+    limit: usize,
+  ) -> Result<Option<String>> {
+    let address = if address.len() == ADDRESS_SIZE {
+      &address[..HASH_SIZE]
+    } else {
+      address
+    };
+    if !is_hash(address) {
+      return Ok(None);
+    }
+    let id = match find_by_hash(self.address_mapper, conn, address)? {
+      Some(record) => record.id(),
+      None => return Ok(None),
+    };
+    let transactions = TransactionRecord::find_by_address(conn, id, limit)?
+      .into_iter()
+      .map(|record| {
+        format!("{{{}}}", fields(&current(self.transaction_mapper, record)))
+      })
+      .collect::<Vec<_>>();
+    Ok(Some(format!(
+      "{{\"address\":{},\"transactions\":[{}]}}",
+      json_string(address),
+      transactions.join(",")
+    )))
+  }
+
+  fn bundle(
+    &self,
+    conn: &mut Connection,
+    bundle: &str,
+  ) -> Result<Option<String>> {
+    if !is_hash(bundle) {
+      return Ok(None);
+    }
+    let (id, is_mst) = match find_by_hash(self.bundle_mapper, conn, bundle)? {
+      Some(record) => (record.id(), record.is_mst()),
+      None => return Ok(None),
+    };
+    let mut transactions = TransactionRecord::find_bundle(conn, id)?
+      .into_iter()
+      .map(|record| {
+        let record = current(self.transaction_mapper, record);
+        (record.current_idx(), fields(&record))
+      })
+      .collect::<Vec<_>>();
+    transactions.sort_by_key(|&(current_idx, _)| current_idx);
+    let transactions = transactions
+      .into_iter()
+      .map(|(_, fields)| format!("{{{}}}", fields))
+      .collect::<Vec<_>>();
+    Ok(Some(format!(
+      "{{\"bundle\":{},\"milestone\":{},\"transactions\":[{}]}}",
+      json_string(bundle),
+      is_mst,
+      transactions.join(",")
+    )))
+  }
+
+  fn txload(
+    &self,
+    conn: &mut Connection,
+    since: Option<f64>,
+  ) -> Result<Option<String>> {
+    let since = match since {
+      Some(since) => since,
+      None => SystemTime::milliseconds_since_epoch()? - DEFAULT_TXLOAD_PERIOD,
+    };
+    let events = event::counts(conn, since)?
+      .into_iter()
+      .map(|(event, count)| format!("{}:{}", json_string(&event), count))
+      .collect::<Vec<_>>();
+    Ok(Some(format!(
+      "{{\"since\":{},\"events\":{{{}}}}}",
+      since,
+      events.join(",")
+    )))
+  }
+
+  fn find_transaction(
+    &self,
+    conn: &mut Connection,
+    hash: &str,
+  ) -> Result<Option<TransactionRecord>> {
+    if !is_hash(hash) {
+      return Ok(None);
+    }
+    find_by_hash(self.transaction_mapper, conn, hash)
+  }
+}
+
+fn fields(record: &TransactionRecord) -> String {
+  format!(
+    "\"hash\":{},\"tag\":{},\"value\":{},\"timestamp\":{},\
+     \"current_idx\":{},\"last_idx\":{},\"milestone\":{},\"confirmed\":{},\
+     \"conftime\":{},\"solid\":{}",
+    json_string(record.hash()),
+    json_string(record.tag()),
+    record.value(),
+    record.timestamp(),
+    record.current_idx(),
+    record.last_idx(),
+    record.is_mst(),
+    record.mst_a(),
+    record.conftime(),
+    record.solid().is_complete()
+  )
+}
+
+fn find_by_hash<T>(
+  mapper: &T,
+  conn: &mut Connection,
+  hash: &str,
+) -> Result<Option<T::Record>>
+where
+  T: Mapper,
+  T::Record: Clone,
+{
+  if let Some((_, record)) = mapper.peek_by_hash(hash) {
+    debug!("Mutex lock");
+    let record = record.lock().unwrap();
+    debug!("Mutex acquire");
+    if record.is_persisted() {
+      return Ok(Some(record.clone()));
+    }
+    return Ok(None);
+  }
+  Ok(T::Record::find_by_hash(conn, hash)?)
+}
+
+fn current<T>(mapper: &T, record: T::Record) -> T::Record
+where
+  T: Mapper,
+  T::Record: Clone,
+{
+  match mapper.peek(record.id()) {
+    Some(cached) => {
+      debug!("Mutex lock");
+      let cached = cached.lock().unwrap();
+      debug!("Mutex acquire");
+      cached.clone()
+    }
+    None => record,
+  }
+}
+
+fn hash_of<T>(
+  mapper: &T,
+  conn: &mut Connection,
+  id: Option<u64>,
+) -> Result<Option<String>>
+where
+  T: Mapper,
+  T::Record: Clone,
+{
+  let id = match id {
+    Some(id) => id,
+    None => return Ok(None),
+  };
+  if let Some(record) = mapper.peek(id) {
+    debug!("Mutex lock");
+    let record = record.lock().unwrap();
+    debug!("Mutex acquire");
+    return Ok(Some(record.hash().to_owned()));
+  }
+  match T::Record::find_by_id(conn, id) {
+    Ok(record) => Ok(Some(record.hash().to_owned())),
+    Err(mapper::Error::RecordNotFound(_)) => Ok(None),
+    Err(err) => Err(err.into()),
+  }
+}
+
+fn hashes(records: &[TransactionRecord]) -> Vec<String> {
+  records
+    .iter()
+    .map(|record| json_string(record.hash()))
+    .collect()
+}
+
+fn json_option(value: Option<String>) -> String {
+  value.map_or_else(|| "null".to_owned(), |value| json_string(&value))
+}
+
+fn is_hash(hash: &str) -> bool {
+  hash.len() == HASH_SIZE && hash.chars().all(utils::is_tryte)
+}
+
+fn param<T: FromStr>(query: &str, name: &str) -> Option<T> {
+  query
+    .split('&')
+    .filter_map(|pair| {
+      let mut parts = pair.splitn(2, '=');
+      match (parts.next(), parts.next()) {
+        (Some(key), Some(value)) if key == name => value.parse().ok(),
+        _ => None,
+      }
+    })
+    .next()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_query_params() {
+    let query = "hash=ABC&limit=10&empty=&flag";
+    assert_eq!(param::<String>(query, "hash"), Some("ABC".to_owned()));
+    assert_eq!(param::<usize>(query, "limit"), Some(10));
+    assert_eq!(param::<String>(query, "empty"), Some(String::new()));
+    assert_eq!(param::<String>(query, "flag"), None);
+    assert_eq!(param::<String>(query, "missing"), None);
+    assert_eq!(param::<usize>(query, "hash"), None);
+    assert_eq!(param::<usize>("", "limit"), None);
+  }
+
+  #[test]
+  fn uses_first_parsable_param() {
+    assert_eq!(param::<usize>("limit=x&limit=5", "limit"), Some(5));
+    assert_eq!(param::<usize>("limit=1&limit=5", "limit"), Some(1));
+  }
+
+  #[test]
+  fn validates_hashes() {
+    let hash = "9".repeat(HASH_SIZE);
+    assert!(is_hash(&hash));
+    assert!(is_hash(&"A".repeat(HASH_SIZE)));
+    assert!(!is_hash(&hash[1..]));
+    assert!(!is_hash(&format!("{}9", hash)));
+    assert!(!is_hash(&"a".repeat(HASH_SIZE)));
+    assert!(!is_hash(&format!("{}1", &hash[1..])));
+  }
+}
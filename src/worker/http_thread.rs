@@ -0,0 +1,310 @@
+use super::{HttpApi, Monitor, Supervisor};
+use mapper::{AddressMapper, BundleMapper, CacheMonitor, TransactionMapper};
+use metrics::Metrics;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, TryLockError};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+use storage::{Connection, Storage};
+use utils::{json_string, DurationUtils};
+
+const READ_TIMEOUT: u64 = 5000;
+const MAX_CONNECTIONS: usize = 64;
+
+pub struct HttpThread {
+  pub address: String,
+  pub storage: Arc<Storage>,
+  pub supervisor: Supervisor,
+  pub metrics: Arc<Metrics>,
+  pub queues: Vec<Box<Monitor>>,
+  pub caches: Vec<Arc<CacheMonitor>>,
+  pub transaction_mapper: Arc<TransactionMapper>,
+  pub address_mapper: Arc<AddressMapper>,
+  pub bundle_mapper: Arc<BundleMapper>,
+  pub ready_idle: u64,
+  pub ready_queue: usize,
+}
+
+struct Shared {
+  idle: Mutex<Vec<Box<Connection>>>,
+  probe: Mutex<Option<Box<Connection>>>,
+  active: AtomicUsize,
+}
+
+struct Response {
+  status: &'static str,
+  content_type: &'static str,
+  body: String,
+}
+
+impl HttpThread {
+  pub fn spawn(self) -> thread::JoinHandle<()> {
+    let listener =
+      TcpListener::bind(&self.address).expect("HTTP listener failure");
+    info!("HTTP listening on {}", self.address);
+    let shared = Arc::new(Shared {
+      idle: Mutex::new(Vec::new()),
+      probe: Mutex::new(None),
+      active: AtomicUsize::new(0),
+    });
+    let http = Arc::new(self);
+    let thread = thread::Builder::new().name("http".into());
+    let thread = thread.spawn(move || {
+      for stream in listener.incoming() {
+        match stream {
+          Ok(stream) => Self::accept(&http, &shared, stream),
+          Err(err) => warn!("HTTP connection failure: {}", err),
+        }
+      }
+    });
+    thread.expect("Thread spawn failure")
+  }
+
+  fn accept(http: &Arc<Self>, shared: &Arc<Shared>, stream: TcpStream) {
+    if shared.active.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+      shared.active.fetch_sub(1, Ordering::SeqCst);
+      warn!("HTTP connection limit of {} reached", MAX_CONNECTIONS);
+      let response = Response::text(
+        "503 Service Unavailable",
+        "Service Unavailable\n".into(),
+      );
+      if let Err(err) = response.write(stream) {
+        warn!("HTTP connection failure: {}", err);
+      }
+      return;
+    }
+    let thread = thread::Builder::new().name("http:conn".into());
+    let spawned = {
+      let (http, shared) = (http.clone(), shared.clone());
+      thread.spawn(move || {
+        if let Err(err) = http.serve(&shared, stream) {
+          warn!("HTTP connection failure: {}", err);
+        }
+        shared.active.fetch_sub(1, Ordering::SeqCst);
+      })
+    };
+    if let Err(err) = spawned {
+      shared.active.fetch_sub(1, Ordering::SeqCst);
+      error!("HTTP thread spawn failure: {}", err);
+    }
+  }
+
+  fn serve(&self, shared: &Shared, stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_millis(READ_TIMEOUT)))?;
+    let mut reader = BufReader::new(stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && header.trim() != "" {
+      header.clear();
+    }
+    let response = {
+      let mut parts = request.split_whitespace();
+      match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => self.route(shared, method, path),
+        _ => Response::text("400 Bad Request", "Bad Request\n".into()),
+      }
+    };
+    response.write(reader.into_inner())
+  }
+
+  fn route(&self, shared: &Shared, method: &str, path: &str) -> Response {
+    let mut parts = path.splitn(2, '?');
+    let path = parts.next().unwrap_or(path);
+    let query = parts.next().unwrap_or("");
+    match (method, path) {
+      ("GET", "/metrics") => Response {
+        status: "200 OK",
+        content_type: "text/plain; version=0.0.4",
+        body: self.metrics.render(&self.queues, &self.caches),
+      },
+      ("GET", "/healthz") => self.health(),
+      ("GET", "/readyz") => self.readiness(shared),
+      ("GET", _) if path.starts_with("/api/") => self.api(shared, path, query),
+      ("GET", _) => Response::text("404 Not Found", "Not Found\n".into()),
+      _ => Response::text(
+        "405 Method Not Allowed",
+        "Method Not Allowed\n".into(),
+      ),
+    }
+  }
+
+  fn health(&self) -> Response {
+    let threads = self.supervisor.threads();
+    let ok = threads.iter().all(|&(_, alive)| alive);
+    let threads = threads
+      .iter()
+      .map(|&(ref name, alive)| format!("{}:{}", json_string(name), alive))
+      .collect::<Vec<_>>();
+    let body = format!(
+      "{{\"status\":{},\"threads\":{{{}}}}}",
+      status(ok),
+      threads.join(",")
+    );
+    Response::json(check_status(ok), body)
+  }
+
+  fn readiness(&self, shared: &Shared) -> Response {
+    let (database_ok, database) = match self.check_database(shared) {
+      Ok(()) => (true, "{\"ok\":true}".to_owned()),
+      Err(err) => (
+        false,
+        format!("{{\"ok\":false,\"error\":{}}}", json_string(&err)),
+      ),
+    };
+    let idle = self.metrics.idle();
+    let source_ok = self.ready_idle == 0 ||
+      idle < Duration::from_millis(self.ready_idle);
+    let source = format!(
+      "{{\"ok\":{},\"idle_ms\":{:.0}}}",
+      source_ok,
+      idle.as_milliseconds()
+    );
+    let mut queues_ok = true;
+    let queues = self
+      .queues
+      .iter()
+      .map(|queue| {
+        let stats = queue.stats();
+        let full = stats.capacity * self.ready_queue <= stats.depth * 100;
+        let ok = stats.capacity == 0 || self.ready_queue == 0 || !full;
+        queues_ok &= ok;
+        format!(
+          "{}:{{\"ok\":{},\"depth\":{},\"capacity\":{}}}",
+          json_string(stats.name),
+          ok,
+          stats.depth,
+          stats.capacity
+        )
+      })
+      .collect::<Vec<_>>();
+    let ok = database_ok && source_ok && queues_ok;
+    let body = format!(
+      "{{\"status\":{},\"database\":{},\"source\":{},\"queues\":{{{}}}}}",
+      status(ok),
+      database,
+      source,
+      queues.join(",")
+    );
+    Response::json(check_status(ok), body)
+  }
+
+  fn check_database(&self, shared: &Shared) -> Result<(), String> {
+    debug!("Mutex lock");
+    let mut probe = match shared.probe.try_lock() {
+      Ok(probe) => probe,
+      Err(TryLockError::Poisoned(err)) => err.into_inner(),
+      Err(TryLockError::WouldBlock) => return Err("reconnecting".to_owned()),
+    };
+    debug!("Mutex acquire");
+    let failure = probe.as_mut().and_then(|conn| conn.schema_version().err());
+    if let Some(err) = failure {
+      warn!("Readiness database failure: {}, reconnecting", err);
+      *probe = None;
+    }
+    if probe.is_none() {
+      let mut conn = self.storage.try_connect().map_err(|err| err.to_string())?;
+      conn.schema_version().map_err(|err| err.to_string())?;
+      *probe = Some(conn);
+    }
+    Ok(())
+  }
+
+  fn api(&self, shared: &Shared, path: &str, query: &str) -> Response {
+    let api = HttpApi {
+      transaction_mapper: &self.transaction_mapper,
+      address_mapper: &self.address_mapper,
+      bundle_mapper: &self.bundle_mapper,
+    };
+    let conn = {
+      debug!("Mutex lock");
+      let mut idle = shared.idle.lock().unwrap();
+      debug!("Mutex acquire");
+      idle.pop()
+    };
+    let conn = match conn {
+      Some(conn) => Ok(conn),
+      None => self.storage.try_connect(),
+    };
+    let mut conn = match conn {
+      Ok(conn) => conn,
+      Err(err) => {
+        warn!("HTTP API database failure: {}", err);
+        return Response::json(
+          "503 Service Unavailable",
+          format!("{{\"error\":{}}}", json_string(&err.to_string())),
+        );
+      }
+    };
+    let result = api.perform(&mut *conn, path, query);
+    if result.is_ok() {
+      debug!("Mutex lock");
+      let mut idle = shared.idle.lock().unwrap();
+      debug!("Mutex acquire");
+      idle.push(conn);
+    }
+    match result {
+      Ok(Some(body)) => Response::json("200 OK", body),
+      Ok(None) => Response::json(
+        "404 Not Found",
+        "{\"error\":\"not found\"}".to_owned(),
+      ),
+      Err(err) => {
+        error!("HTTP API failure: {}", err);
+        Response::json(
+          "500 Internal Server Error",
+          format!("{{\"error\":{}}}", json_string(&err.to_string())),
+        )
+      }
+    }
+  }
+}
+
+impl Response {
+  fn text(status: &'static str, body: String) -> Self {
+    Self {
+      status,
+      content_type: "text/plain",
+      body,
+    }
+  }
+
+  fn json(status: &'static str, body: String) -> Self {
+    Self {
+      status,
+      content_type: "application/json",
+      body,
+    }
+  }
+
+  fn write(&self, mut stream: TcpStream) -> io::Result<()> {
+    write!(
+      stream,
+      "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+       Connection: close\r\n\r\n",
+      self.status,
+      self.content_type,
+      self.body.len()
+    )?;
+    stream.write_all(self.body.as_bytes())?;
+    stream.flush()
+  }
+}
+
+fn check_status(ok: bool) -> &'static str {
+  if ok {
+    "200 OK"
+  } else {
+    "503 Service Unavailable"
+  }
+}
+
+fn status(ok: bool) -> &'static str {
+  if ok {
+    "\"ok\""
+  } else {
+    "\"fail\""
+  }
+}
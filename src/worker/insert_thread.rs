@@ -1,45 +1,51 @@
+use dead_letter::{self, Stage};
+use job_queue::{self, JobStore, Pending};
 use mapper::{AddressMapper, BundleMapper, TransactionMapper};
 use message::TransactionMessage;
-use mysql;
-use std::sync::{mpsc, Arc};
+use metrics::{self, Metrics};
+use std::fmt::Display;
+use std::sync::Arc;
 use std::thread;
-use std::time::Instant;
-use utils::{self, DurationUtils, MysqlConnUtils};
-use worker::{ApproveJob, CalculateJob, SolidateJob};
+use std::time::{Instant, SystemTime};
+use storage::{Connection, Storage};
+use utils::{self, DurationUtils, SystemTimeUtils};
+use worker::{ApproveJob, CalculateJob, Queue, SolidateJob};
 
 const HASH_SIZE: usize = 81;
 
-pub struct InsertThread<'a> {
-  pub insert_rx: mpsc::Receiver<String>,
-  pub approve_tx: mpsc::Sender<ApproveJob>,
-  pub solidate_tx: mpsc::Sender<SolidateJob>,
-  pub calculate_tx: mpsc::Sender<CalculateJob>,
-  pub mysql_uri: &'a str,
-  pub retry_interval: u64,
+#[derive(Clone)]
+pub struct InsertThread {
+  pub insert_queue: Queue<String>,
+  pub approve_queue: Queue<Pending<ApproveJob>>,
+  pub solidate_queue: Queue<Pending<SolidateJob>>,
+  pub calculate_queue: Queue<Pending<CalculateJob>>,
+  pub storage: Arc<Storage>,
+  pub job_store: Arc<JobStore>,
   pub transaction_mapper: Arc<TransactionMapper>,
   pub address_mapper: Arc<AddressMapper>,
   pub bundle_mapper: Arc<BundleMapper>,
-  pub milestone_address: &'a str,
+  pub metrics: Arc<Metrics>,
+  pub milestone_address: String,
   pub milestone_start_index: String,
 }
 
-impl<'a> InsertThread<'a> {
-  pub fn spawn(self) {
+impl InsertThread {
+  pub fn spawn(self) -> thread::JoinHandle<()> {
     let Self {
-      insert_rx,
-      approve_tx,
-      solidate_tx,
-      calculate_tx,
-      mysql_uri,
-      retry_interval,
+      insert_queue,
+      approve_queue,
+      solidate_queue,
+      calculate_queue,
+      storage,
+      job_store,
       transaction_mapper,
       address_mapper,
       bundle_mapper,
+      metrics,
       milestone_address,
       milestone_start_index,
     } = self;
-    let milestone_address = milestone_address.to_owned();
-    let mut conn = mysql::Conn::new_retry(mysql_uri, retry_interval);
+    let mut conn = storage.connect();
     let null_hash = utils::trits_string(0, HASH_SIZE)
       .expect("Can't convert null_hash to trits");
     let thread = thread::Builder::new().name("insert".into());
@@ -47,55 +53,84 @@ impl<'a> InsertThread<'a> {
       let transaction_mapper = &*transaction_mapper;
       let address_mapper = &*address_mapper;
       let bundle_mapper = &*bundle_mapper;
-      loop {
-        let message = insert_rx.recv().expect("Thread communication failure");
+      let mut parse_failures = 0;
+      while let Some(message) = insert_queue.pop() {
+        metrics.message();
         let duration = Instant::now();
-        let result = TransactionMessage::parse(
+        let result = TransactionMessage::parse_topic(
           &message,
           &milestone_address,
           &milestone_start_index,
         );
         match result {
-          Ok(message) => {
-            let result = message.perform(
-              &mut conn,
+          Ok(transaction) => {
+            let result = transaction.perform(
+              &mut *conn,
               transaction_mapper,
               address_mapper,
               bundle_mapper,
               &null_hash,
+              Some(&*job_store),
             );
             let duration = duration.elapsed().as_milliseconds();
+            metrics.observe(metrics::Stage::Insert, duration);
             match result {
               Ok((approve_data, solidate_data, calculate_data)) => {
-                info!("{:.3}ms {}", duration, message.hash());
+                info!("{:.3}ms {}", duration, transaction.hash());
                 if let Some(approve_data) = approve_data {
-                  approve_tx
-                    .send(approve_data)
-                    .expect("Thread communication failure");
+                  job_queue::enqueue(&mut *conn, &approve_queue, approve_data);
                 }
                 if let Some(solidate_data) = solidate_data {
-                  solidate_tx
-                    .send(solidate_data)
-                    .expect("Thread communication failure");
+                  job_queue::enqueue(
+                    &mut *conn,
+                    &solidate_queue,
+                    solidate_data,
+                  );
                 }
                 if let Some(calculate_data) = calculate_data {
-                  calculate_tx
-                    .send(calculate_data)
-                    .expect("Thread communication failure");
+                  job_queue::enqueue(
+                    &mut *conn,
+                    &calculate_queue,
+                    calculate_data,
+                  );
                 }
               }
               Err(err) => {
+                metrics.failure(metrics::Stage::Insert);
                 error!("{:.3}ms Processing failure: {}", duration, err);
+                dead_letter(&mut *conn, &message, Stage::Process, &err);
               }
             }
           }
           Err(err) => {
             let duration = duration.elapsed().as_milliseconds();
-            error!("{:.3}ms Parsing failure: {}", duration, err);
+            parse_failures += 1;
+            metrics.parse_failure();
+            error!(
+              "{:.3}ms Parsing failure #{}: {}",
+              duration,
+              parse_failures,
+              err
+            );
+            dead_letter(&mut *conn, &message, Stage::Parse, &err);
           }
         }
       }
     });
-    thread.expect("Thread spawn failure");
+    thread.expect("Thread spawn failure")
+  }
+}
+
+fn dead_letter<T: Display>(
+  conn: &mut Connection,
+  message: &str,
+  stage: Stage,
+  err: &T,
+) {
+  let timestamp = SystemTime::milliseconds_since_epoch().unwrap_or(0.0);
+  let err = err.to_string();
+  if let Err(err) = dead_letter::insert(conn, message, stage, &err, timestamp)
+  {
+    error!("Dead letter failure: {}", err);
   }
 }
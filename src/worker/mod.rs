@@ -1,15 +1,35 @@
 mod zmq_loop;
+mod zmq_source;
 mod insert_thread;
 mod update_thread;
 mod approve_thread;
 mod solidate_thread;
 mod calculate_threads;
+mod node_thread;
+mod dead_letter_retry;
+mod recorder;
+mod replay_loop;
+mod queue;
+mod monitor_thread;
+mod http_thread;
+mod http_api;
+mod supervisor;
 mod error;
 
 pub use self::approve_thread::{ApproveJob, ApproveThread};
 pub use self::calculate_threads::{CalculateJob, CalculateThreads};
+pub use self::dead_letter_retry::DeadLetterRetry;
 pub use self::error::{Error, Result};
+pub use self::http_api::HttpApi;
+pub use self::http_thread::HttpThread;
 pub use self::insert_thread::InsertThread;
+pub use self::monitor_thread::MonitorThread;
+pub use self::node_thread::NodeThread;
+pub use self::queue::{Monitor, Overflow, Push, Queue, QueueStats};
+pub use self::recorder::Recorder;
+pub use self::replay_loop::ReplayLoop;
 pub use self::solidate_thread::{SolidateJob, SolidateThread};
+pub use self::supervisor::Supervisor;
 pub use self::update_thread::UpdateThread;
-pub use self::zmq_loop::ZmqLoop;
+pub use self::zmq_loop::{SourceStats, ZmqLoop};
+pub use self::zmq_source::ZmqSource;
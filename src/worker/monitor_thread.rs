@@ -0,0 +1,46 @@
+use super::Monitor;
+use mapper::CacheMonitor;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+const MONITOR_INTERVAL: u64 = 60;
+
+pub struct MonitorThread {
+  pub queues: Vec<Box<Monitor>>,
+  pub caches: Vec<Arc<CacheMonitor>>,
+}
+
+impl MonitorThread {
+  pub fn spawn(self) -> thread::JoinHandle<()> {
+    let Self { queues, caches } = self;
+    let thread = thread::Builder::new().name("monitor".into());
+    let thread = thread.spawn(move || loop {
+      thread::sleep(Duration::from_secs(MONITOR_INTERVAL));
+      for queue in &queues {
+        let stats = queue.stats();
+        info!(
+          "Queue `{}` depth: {}/{}, dropped: {}, coalesced: {}",
+          stats.name,
+          stats.depth,
+          stats.capacity,
+          stats.dropped,
+          stats.coalesced
+        );
+      }
+      for cache in &caches {
+        let stats = cache.stats();
+        info!(
+          "Cache `{}` entries: {}/{}, hits: {}, misses: {}, evictions: {}",
+          stats.name,
+          stats.entries,
+          stats.capacity,
+          stats.hits,
+          stats.misses,
+          stats.evictions
+        );
+      }
+    });
+    thread.expect("Thread spawn failure")
+  }
+}
@@ -0,0 +1,55 @@
+use message::NodeMessage;
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+use storage::Storage;
+use utils::DurationUtils;
+use worker::Queue;
+
+#[derive(Clone)]
+pub struct NodeThread {
+  pub node_queue: Queue<String>,
+  pub storage: Arc<Storage>,
+}
+
+impl NodeThread {
+  pub fn spawn(self) -> thread::JoinHandle<()> {
+    let Self {
+      node_queue,
+      storage,
+    } = self;
+    let mut conn = storage.connect();
+    let thread = thread::Builder::new().name("node".into());
+    let thread = thread.spawn(move || {
+      let mut parse_failures = 0;
+      while let Some(message) = node_queue.pop() {
+        let duration = Instant::now();
+        match NodeMessage::parse(&message) {
+          Ok(message) => {
+            let result = message.perform(&mut *conn);
+            let duration = duration.elapsed().as_milliseconds();
+            match result {
+              Ok(()) => {
+                info!("{:.3}ms {:?}", duration, message);
+              }
+              Err(err) => {
+                error!("{:.3}ms Processing failure: {}", duration, err);
+              }
+            }
+          }
+          Err(err) => {
+            let duration = duration.elapsed().as_milliseconds();
+            parse_failures += 1;
+            error!(
+              "{:.3}ms Parsing failure #{}: {}",
+              duration,
+              parse_failures,
+              err
+            );
+          }
+        }
+      }
+    });
+    thread.expect("Thread spawn failure")
+  }
+}
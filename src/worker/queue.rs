@@ -0,0 +1,260 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Overflow {
+  Block,
+  Coalesce,
+  Drop,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Push {
+  Queued,
+  Coalesced,
+  Dropped,
+  Closed,
+}
+
+pub struct Queue<T> {
+  inner: Arc<Inner<T>>,
+}
+
+#[derive(Debug)]
+pub struct QueueStats {
+  pub name: &'static str,
+  pub depth: usize,
+  pub capacity: usize,
+  pub dropped: usize,
+  pub coalesced: usize,
+}
+
+pub trait Monitor: Send + Sync {
+  fn stats(&self) -> QueueStats;
+}
+
+struct Inner<T> {
+  name: &'static str,
+  capacity: usize,
+  overflow: Overflow,
+  state: Mutex<State<T>>,
+  not_empty: Condvar,
+  not_full: Condvar,
+  dropped: AtomicUsize,
+  coalesced: AtomicUsize,
+}
+
+struct State<T> {
+  items: VecDeque<T>,
+  closed: bool,
+}
+
+impl Overflow {
+  pub fn parse(policy: &str) -> Option<Self> {
+    match policy {
+      "block" => Some(Overflow::Block),
+      "coalesce" => Some(Overflow::Coalesce),
+      "drop" => Some(Overflow::Drop),
+      _ => None,
+    }
+  }
+
+  pub fn as_str(&self) -> &'static str {
+    match *self {
+      Overflow::Block => "block",
+      Overflow::Coalesce => "coalesce",
+      Overflow::Drop => "drop",
+    }
+  }
+}
+
+impl<T> Queue<T> {
+  pub fn new(name: &'static str, capacity: usize, overflow: Overflow) -> Self {
+    Self {
+      inner: Arc::new(Inner {
+        name,
+        capacity: capacity.max(1),
+        overflow,
+        state: Mutex::new(State {
+          items: VecDeque::new(),
+          closed: false,
+        }),
+        not_empty: Condvar::new(),
+        not_full: Condvar::new(),
+        dropped: AtomicUsize::new(0),
+        coalesced: AtomicUsize::new(0),
+      }),
+    }
+  }
+
+  pub fn pop(&self) -> Option<T> {
+    let mut state = self.inner.state.lock().unwrap();
+    loop {
+      if let Some(item) = state.items.pop_front() {
+        self.inner.not_full.notify_one();
+        return Some(item);
+      }
+      if state.closed {
+        return None;
+      }
+      state = self.inner.not_empty.wait(state).unwrap();
+    }
+  }
+
+  pub fn close(&self) {
+    self.inner.state.lock().unwrap().closed = true;
+    self.inner.not_empty.notify_all();
+    self.inner.not_full.notify_all();
+  }
+
+  pub fn is_closed(&self) -> bool {
+    self.inner.state.lock().unwrap().closed
+  }
+
+  pub fn len(&self) -> usize {
+    self.inner.state.lock().unwrap().items.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+}
+
+impl<T: PartialEq> Queue<T> {
+  pub fn push(&self, item: T) -> Push {
+    let inner = &*self.inner;
+    let mut state = inner.state.lock().unwrap();
+    if inner.overflow == Overflow::Coalesce && state.items.contains(&item) {
+      inner.coalesced.fetch_add(1, Ordering::Relaxed);
+      return Push::Coalesced;
+    }
+    while !state.closed && state.items.len() >= inner.capacity {
+      if inner.overflow == Overflow::Drop {
+        let dropped = inner.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+        if dropped.is_power_of_two() {
+          warn!("Queue `{}` is full, {} items dropped", inner.name, dropped);
+        }
+        return Push::Dropped;
+      }
+      state = inner.not_full.wait(state).unwrap();
+    }
+    if state.closed {
+      debug!("Queue `{}` is closed", inner.name);
+      return Push::Closed;
+    }
+    state.items.push_back(item);
+    inner.not_empty.notify_one();
+    Push::Queued
+  }
+}
+
+impl<T> Clone for Queue<T> {
+  fn clone(&self) -> Self {
+    Self {
+      inner: self.inner.clone(),
+    }
+  }
+}
+
+impl<T: Send> Monitor for Queue<T> {
+  fn stats(&self) -> QueueStats {
+    QueueStats {
+      name: self.inner.name,
+      depth: self.len(),
+      capacity: self.inner.capacity,
+      dropped: self.inner.dropped.load(Ordering::Relaxed),
+      coalesced: self.inner.coalesced.load(Ordering::Relaxed),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::thread;
+
+  #[test]
+  fn pops_in_push_order() {
+    let queue = Queue::new("test", 4, Overflow::Block);
+    assert_eq!(queue.push(1), Push::Queued);
+    assert_eq!(queue.push(2), Push::Queued);
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.pop(), Some(2));
+    assert!(queue.is_empty());
+  }
+
+  #[test]
+  fn coalesces_queued_duplicates() {
+    let queue = Queue::new("test", 4, Overflow::Coalesce);
+    assert_eq!(queue.push(1), Push::Queued);
+    assert_eq!(queue.push(1), Push::Coalesced);
+    assert_eq!(queue.push(2), Push::Queued);
+    assert_eq!(queue.len(), 2);
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.push(1), Push::Queued);
+    let stats = queue.stats();
+    assert_eq!(stats.depth, 2);
+    assert_eq!(stats.coalesced, 1);
+    assert_eq!(stats.dropped, 0);
+  }
+
+  #[test]
+  fn drops_when_full() {
+    let queue = Queue::new("test", 2, Overflow::Drop);
+    assert_eq!(queue.push(1), Push::Queued);
+    assert_eq!(queue.push(2), Push::Queued);
+    assert_eq!(queue.push(3), Push::Dropped);
+    assert_eq!(queue.push(3), Push::Dropped);
+    let stats = queue.stats();
+    assert_eq!(stats.depth, 2);
+    assert_eq!(stats.capacity, 2);
+    assert_eq!(stats.dropped, 2);
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.push(3), Push::Queued);
+  }
+
+  #[test]
+  fn blocks_until_popped() {
+    let queue = Queue::new("test", 1, Overflow::Block);
+    assert_eq!(queue.push(1), Push::Queued);
+    let producer = {
+      let queue = queue.clone();
+      thread::spawn(move || queue.push(2))
+    };
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(producer.join().unwrap(), Push::Queued);
+    assert_eq!(queue.pop(), Some(2));
+  }
+
+  #[test]
+  fn close_wakes_blocked_producer() {
+    let queue = Queue::new("test", 1, Overflow::Block);
+    assert_eq!(queue.push(1), Push::Queued);
+    let producer = {
+      let queue = queue.clone();
+      thread::spawn(move || queue.push(2))
+    };
+    queue.close();
+    assert_eq!(producer.join().unwrap(), Push::Closed);
+    assert!(queue.is_closed());
+    assert_eq!(queue.push(3), Push::Closed);
+    assert_eq!(queue.pop(), Some(1));
+    assert_eq!(queue.pop(), None);
+  }
+
+  #[test]
+  fn capacity_is_at_least_one() {
+    let queue = Queue::new("test", 0, Overflow::Drop);
+    assert_eq!(queue.push(1), Push::Queued);
+    assert_eq!(queue.push(2), Push::Dropped);
+  }
+
+  #[test]
+  fn parses_overflow_policies() {
+    for &overflow in &[Overflow::Block, Overflow::Coalesce, Overflow::Drop] {
+      assert_eq!(Overflow::parse(overflow.as_str()), Some(overflow));
+    }
+    assert_eq!(Overflow::parse("spill"), None);
+  }
+}
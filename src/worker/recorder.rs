@@ -0,0 +1,24 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::time::SystemTime;
+use utils::SystemTimeUtils;
+
+pub struct Recorder {
+  writer: BufWriter<File>,
+}
+
+impl Recorder {
+  pub fn open(path: &str) -> io::Result<Self> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    Ok(Self {
+      writer: BufWriter::new(file),
+    })
+  }
+
+  pub fn record(&mut self, message: &str) -> io::Result<()> {
+    let arrival = SystemTime::milliseconds_since_epoch()
+      .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+    writeln!(self.writer, "{:.0} {}", arrival, message)?;
+    self.writer.flush()
+  }
+}
@@ -0,0 +1,72 @@
+use super::{zmq_loop, Queue};
+use metrics::Metrics;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub struct ReplayLoop {
+  pub reader: BufReader<File>,
+  pub paced: bool,
+  pub insert_queue: Queue<String>,
+  pub node_queue: Queue<String>,
+  pub metrics: Arc<Metrics>,
+}
+
+impl ReplayLoop {
+  pub fn spawn(self) -> thread::JoinHandle<()> {
+    let thread = thread::Builder::new().name("replay".into());
+    let thread = thread.spawn(move || self.run());
+    thread.expect("Thread spawn failure")
+  }
+
+  fn run(self) {
+    let Self {
+      reader,
+      paced,
+      insert_queue,
+      node_queue,
+      metrics,
+    } = self;
+    let (start, mut first_arrival, mut count) = (Instant::now(), None, 0);
+    for (i, line) in reader.lines().enumerate() {
+      if insert_queue.is_closed() {
+        break;
+      }
+      let line = match line {
+        Ok(line) => line,
+        Err(err) => {
+          error!("Replay read failure: {}", err);
+          break;
+        }
+      };
+      let mut chunks = line.splitn(2, ' ');
+      let arrival = chunks.next().and_then(|arrival| arrival.parse().ok());
+      match (arrival, chunks.next()) {
+        (Some(arrival), Some(message)) => {
+          if paced {
+            let first_arrival = *first_arrival.get_or_insert(arrival);
+            wait(start, arrival - first_arrival);
+          }
+          metrics.received();
+          info!("{}", message);
+          zmq_loop::dispatch(&insert_queue, &node_queue, message.to_owned());
+          count += 1;
+        }
+        _ => {
+          warn!("Malformed record at line {}", i + 1);
+        }
+      }
+    }
+    info!("Replay finished: {} messages", count);
+  }
+}
+
+fn wait(start: Instant, offset: f64) {
+  let offset = Duration::from_millis(offset.max(0.0) as u64);
+  let elapsed = start.elapsed();
+  if offset > elapsed {
+    thread::sleep(offset - elapsed);
+  }
+}
@@ -1,55 +1,64 @@
 use super::Result;
 use event;
+use job_queue::{Durable, JobStore, Pending};
 use mapper::{TransactionMapper, TransactionRecord};
-use mysql;
+use metrics::{Metrics, Stage};
 use solid::Solidate;
 use std::collections::{HashSet, VecDeque};
-use std::sync::{mpsc, Arc, Mutex};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Instant, SystemTime};
-use utils::{DurationUtils, MysqlConnUtils, SystemTimeUtils};
+use storage::{Connection, Storage};
+use utils::{DurationUtils, SystemTimeUtils};
+use worker::Queue;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct SolidateJob {
   pivot_id: u64,
   height: i32,
 }
 
-pub struct SolidateThread<'a> {
-  pub solidate_rx: mpsc::Receiver<SolidateJob>,
-  pub mysql_uri: &'a str,
-  pub retry_interval: u64,
+#[derive(Clone)]
+pub struct SolidateThread {
+  pub solidate_queue: Queue<Pending<SolidateJob>>,
+  pub storage: Arc<Storage>,
+  pub job_store: Arc<JobStore>,
+  pub metrics: Arc<Metrics>,
   pub transaction_mapper: Arc<TransactionMapper>,
 }
 
-impl<'a> SolidateThread<'a> {
-  pub fn spawn(self) {
+impl SolidateThread {
+  pub fn spawn(self) -> thread::JoinHandle<()> {
     let Self {
-      solidate_rx,
-      mysql_uri,
-      retry_interval,
+      solidate_queue,
+      storage,
+      job_store,
+      metrics,
       transaction_mapper,
     } = self;
-    let mut conn = mysql::Conn::new_retry(mysql_uri, retry_interval);
+    let mut conn = storage.connect();
     let thread = thread::Builder::new().name("solidate".into());
     let thread = thread.spawn(move || {
       let transaction_mapper = &*transaction_mapper;
-      loop {
-        let job = solidate_rx.recv().expect("Thread communication failure");
+      while let Some(pending) = solidate_queue.pop() {
+        let job = &pending.job;
         let duration = Instant::now();
-        let result = job.perform(&mut conn, transaction_mapper);
+        let result = job.perform(&mut *conn, transaction_mapper);
         let duration = duration.elapsed().as_milliseconds();
+        metrics.observe(Stage::Solidate, duration);
         match result {
           Ok(()) => {
             info!("{:.3}ms {:?}", duration, job);
+            job_store.ack(&pending);
           }
           Err(err) => {
+            metrics.failure(Stage::Solidate);
             error!("{:.3}ms {}", duration, err);
           }
         }
       }
     });
-    thread.expect("Thread spawn failure");
+    thread.expect("Thread spawn failure")
   }
 }
 
@@ -60,7 +69,7 @@ impl SolidateJob {
 
   pub fn perform(
     &self,
-    conn: &mut mysql::Conn,
+    conn: &mut Connection,
     transaction_mapper: &TransactionMapper,
   ) -> Result<()> {
     let (timestamp, mut counter) = (SystemTime::milliseconds_since_epoch()?, 0);
@@ -98,6 +107,22 @@ impl SolidateJob {
   }
 }
 
+impl Durable for SolidateJob {
+  const KIND: &'static str = "solidate";
+
+  fn encode(&self) -> String {
+    format!("{} {}", self.pivot_id, self.height)
+  }
+
+  fn decode(data: &str) -> Option<Self> {
+    let mut fields = data.split(' ');
+    Some(Self::new(
+      fields.next()?.parse().ok()?,
+      fields.next()?.parse().ok()?,
+    ))
+  }
+}
+
 fn solidate(
   nodes: &mut VecDeque<(u64, Option<i32>)>,
   visited: &mut HashSet<u64>,
@@ -131,3 +156,23 @@ fn solidate(
   }
   Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn round_trips_jobs() {
+    let job = SolidateJob::new(42, -1);
+    assert_eq!(job.encode(), "42 -1");
+    assert_eq!(SolidateJob::decode(&job.encode()), Some(job));
+  }
+
+  #[test]
+  fn rejects_malformed_jobs() {
+    assert_eq!(SolidateJob::decode(""), None);
+    assert_eq!(SolidateJob::decode("42"), None);
+    assert_eq!(SolidateJob::decode("42 x"), None);
+    assert_eq!(SolidateJob::decode("-42 1"), None);
+  }
+}
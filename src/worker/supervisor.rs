@@ -0,0 +1,129 @@
+use std::any::Any;
+use std::collections::{BTreeMap, VecDeque};
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use utils::DurationUtils;
+
+const RESTART_DELAY: u64 = 1000;
+
+#[derive(Clone)]
+pub struct Supervisor {
+  restart_limit: usize,
+  restart_window: Duration,
+  threads: Arc<Mutex<BTreeMap<String, bool>>>,
+  recover: Arc<Fn() + Send + Sync>,
+}
+
+impl Supervisor {
+  pub fn new<F>(restart_limit: usize, restart_window: u64, recover: F) -> Self
+  where
+    F: Fn() + Send + Sync + 'static,
+  {
+    Self {
+      restart_limit,
+      restart_window: Duration::from_millis(restart_window),
+      threads: Arc::new(Mutex::new(BTreeMap::new())),
+      recover: Arc::new(recover),
+    }
+  }
+
+  pub fn spawn<F>(&self, name: String, start: F) -> thread::JoinHandle<()>
+  where
+    F: Fn() -> thread::JoinHandle<()> + Send + 'static,
+  {
+    let Self {
+      restart_limit,
+      restart_window,
+      threads,
+      recover,
+    } = self.clone();
+    let thread = thread::Builder::new().name(format!("supervise:{}", name));
+    let thread = thread.spawn(move || {
+      let mut restarts = VecDeque::new();
+      loop {
+        let thread = start();
+        set_alive(&threads, &name, true);
+        let result = thread.join();
+        set_alive(&threads, &name, false);
+        let payload = match result {
+          Ok(()) => break,
+          Err(payload) => payload,
+        };
+        error!("Thread `{}` panicked: {}", name, panic_message(&*payload));
+        let now = Instant::now();
+        while restarts.front().map_or(false, |&restart| {
+          now.duration_since(restart) >= restart_window
+        }) {
+          restarts.pop_front();
+        }
+        restarts.push_back(now);
+        if restarts.len() > restart_limit {
+          error!(
+            "Restart limit of `{}` exceeded ({} within {}ms), exiting",
+            name,
+            restart_limit,
+            restart_window.as_milliseconds()
+          );
+          process::exit(1);
+        }
+        warn!(
+          "Restarting `{}` ({}/{})",
+          name,
+          restarts.len(),
+          restart_limit
+        );
+        thread::sleep(Duration::from_millis(RESTART_DELAY));
+        recover();
+      }
+    });
+    thread.expect("Thread spawn failure")
+  }
+
+  pub fn watch(&self, name: String, thread: thread::JoinHandle<()>) {
+    let threads = self.threads.clone();
+    set_alive(&threads, &name, true);
+    let watcher = thread::Builder::new().name(format!("watch:{}", name));
+    let watcher = watcher.spawn(move || {
+      let result = thread.join();
+      set_alive(&threads, &name, false);
+      if let Err(payload) = result {
+        error!("Thread `{}` panicked: {}", name, panic_message(&*payload));
+        process::exit(1);
+      }
+    });
+    watcher.expect("Thread spawn failure");
+  }
+
+  pub fn threads(&self) -> Vec<(String, bool)> {
+    debug!("Mutex lock");
+    let threads = self.threads.lock().unwrap();
+    debug!("Mutex acquire");
+    threads
+      .iter()
+      .map(|(name, &alive)| (name.clone(), alive))
+      .collect()
+  }
+}
+
+fn set_alive(
+  threads: &Mutex<BTreeMap<String, bool>>,
+  name: &str,
+  alive: bool,
+) {
+  debug!("Mutex lock");
+  let mut threads = threads.lock().unwrap();
+  debug!("Mutex acquire");
+  threads.insert(name.to_owned(), alive);
+}
+
+fn panic_message(payload: &(Any + Send)) -> &str {
+  if let Some(message) = payload.downcast_ref::<&str>() {
+    message
+  } else if let Some(message) = payload.downcast_ref::<String>() {
+    message
+  } else {
+    "unknown panic payload"
+  }
+}
@@ -1,82 +1,194 @@
 use super::Result;
-use mapper::{AddressMapper, BundleMapper, Mapper, TransactionMapper};
-use mysql;
+use job_queue::JobStore;
+use mapper::{AddressMapper, BundleMapper, DirtyStats, Mapper,
+             TransactionMapper};
+use metrics::{Metrics, Stage};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use std::time::{Duration, Instant};
-use utils::{DurationUtils, MysqlConnUtils};
+use storage::{Connection, Storage};
+use utils::DurationUtils;
 
-pub struct UpdateThread<'a> {
-  pub mysql_uri: &'a str,
-  pub retry_interval: u64,
+const FLUSH_CHECK_INTERVAL: u64 = 100;
+
+#[derive(Clone)]
+pub struct UpdateThread {
+  pub storage: Arc<Storage>,
+  pub job_store: Arc<JobStore>,
+  pub metrics: Arc<Metrics>,
   pub update_interval: u64,
+  pub flush_threshold: usize,
+  pub flush_age: u64,
   pub generation_limit: usize,
+  pub batch_size: usize,
   pub transaction_mapper: Arc<TransactionMapper>,
   pub address_mapper: Arc<AddressMapper>,
   pub bundle_mapper: Arc<BundleMapper>,
+  pub stop: Arc<AtomicBool>,
+}
+
+struct FlushPolicy {
+  interval: Duration,
+  threshold: Option<usize>,
+  age: Option<Duration>,
 }
 
-impl<'a> UpdateThread<'a> {
-  pub fn spawn(self) {
+impl UpdateThread {
+  pub fn spawn(self) -> thread::JoinHandle<()> {
     let Self {
-      mysql_uri,
-      retry_interval,
+      storage,
+      job_store,
+      metrics,
       update_interval,
+      flush_threshold,
+      flush_age,
       generation_limit,
+      batch_size,
       transaction_mapper,
       address_mapper,
       bundle_mapper,
+      stop,
     } = self;
-    let update_interval = Duration::from_millis(update_interval);
-    let mut conn = mysql::Conn::new_retry(mysql_uri, retry_interval);
+    let policy = FlushPolicy {
+      interval: Duration::from_millis(update_interval),
+      threshold: if flush_threshold > 0 {
+        Some(flush_threshold)
+      } else {
+        None
+      },
+      age: if flush_age > 0 {
+        Some(Duration::from_millis(flush_age))
+      } else {
+        None
+      },
+    };
+    let mut conn = storage.connect();
     let thread = thread::Builder::new().name("update".into());
     let thread = thread.spawn(move || {
       let transaction_mapper = &*transaction_mapper;
       let address_mapper = &*address_mapper;
       let bundle_mapper = &*bundle_mapper;
-      loop {
-        thread::sleep(update_interval);
-        let duration = Instant::now();
+      let mut last_flush = Instant::now();
+      while !stop.load(Ordering::SeqCst) {
+        thread::sleep(policy.check_interval());
+        let dirty = [
+          transaction_mapper.dirty(),
+          address_mapper.dirty(),
+          bundle_mapper.dirty(),
+        ];
+        let reason = match policy.reason(last_flush, &dirty) {
+          Some(reason) => reason,
+          None => continue,
+        };
+        last_flush = Instant::now();
         let result = perform(
-          &mut conn,
+          &mut *conn,
+          &job_store,
           transaction_mapper,
           generation_limit,
+          batch_size,
           address_mapper,
           bundle_mapper,
         );
-        let duration = duration.elapsed().as_milliseconds();
+        let duration = last_flush.elapsed().as_milliseconds();
+        metrics.observe(Stage::Update, duration);
         match result {
-          Ok((updated, cleaned)) => {
+          Ok((updated, cleaned, evicted)) => {
+            metrics.pruned(cleaned);
             info!(
-              "{:.3}ms updated: {}, cleaned: {}",
+              "{:.3}ms {} flush, dirty: tx {}, address {}, bundle {}, \
+               updated: {} ({:.0} rows/s), cleaned: {}, evicted: {}",
               duration,
+              reason,
+              dirty[0].count,
+              dirty[1].count,
+              dirty[2].count,
               updated,
-              cleaned
+              rate(updated, duration),
+              cleaned,
+              evicted
             );
           }
           Err(err) => {
+            metrics.failure(Stage::Update);
             error!("{:.3}ms {}", duration, err);
           }
         }
       }
     });
-    thread.expect("Thread spawn failure");
+    thread.expect("Thread spawn failure")
+  }
+}
+
+impl FlushPolicy {
+  fn check_interval(&self) -> Duration {
+    if self.threshold.is_some() || self.age.is_some() {
+      self.interval.min(Duration::from_millis(FLUSH_CHECK_INTERVAL))
+    } else {
+      self.interval
+    }
+  }
+
+  fn reason(
+    &self,
+    last_flush: Instant,
+    dirty: &[DirtyStats],
+  ) -> Option<&'static str> {
+    if last_flush.elapsed() >= self.interval {
+      return Some("timer");
+    }
+    if let Some(threshold) = self.threshold {
+      if dirty.iter().map(|stats| stats.count).sum::<usize>() >= threshold {
+        return Some("threshold");
+      }
+    }
+    if let Some(age) = self.age {
+      let expired = dirty
+        .iter()
+        .filter_map(|stats| stats.oldest)
+        .any(|oldest| oldest.elapsed() >= age);
+      if expired {
+        return Some("age");
+      }
+    }
+    None
   }
 }
 
 fn perform(
-  conn: &mut mysql::Conn,
+  conn: &mut Connection,
+  job_store: &JobStore,
   transaction_mapper: &TransactionMapper,
   generation_limit: usize,
+  batch_size: usize,
   address_mapper: &AddressMapper,
   bundle_mapper: &BundleMapper,
-) -> Result<(usize, usize)> {
-  let (mut updated, mut cleaned) = (0, 0);
-  updated += transaction_mapper.update(conn)?;
+) -> Result<(usize, usize, usize)> {
+  let (mut updated, mut cleaned, mut evicted) = (0, 0, 0);
+  let jobs = job_store.take_acked();
+  match transaction_mapper.update(conn, batch_size, &jobs) {
+    Ok(count) => updated += count,
+    Err(err) => {
+      job_store.restore(jobs);
+      return Err(err.into());
+    }
+  }
   cleaned += transaction_mapper.prune(generation_limit);
-  updated += address_mapper.update(conn)?;
+  evicted += transaction_mapper.evict();
+  updated += address_mapper.update(conn, batch_size, &[])?;
   cleaned += address_mapper.prune(generation_limit);
-  updated += bundle_mapper.update(conn)?;
+  evicted += address_mapper.evict();
+  updated += bundle_mapper.update(conn, batch_size, &[])?;
   cleaned += bundle_mapper.prune(generation_limit);
-  Ok((updated, cleaned))
+  evicted += bundle_mapper.evict();
+  Ok((updated, cleaned, evicted))
+}
+
+fn rate(rows: usize, duration: f64) -> f64 {
+  if duration > 0.0 {
+    rows as f64 * 1000.0 / duration
+  } else {
+    0.0
+  }
 }
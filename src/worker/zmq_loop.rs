@@ -1,29 +1,176 @@
-use std::sync::mpsc;
-use zmq;
+use super::{Queue, Recorder};
+use metrics::Metrics;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use utils::DurationUtils;
+
+const DEDUP_CAPACITY: usize = 100_000;
+const STATS_INTERVAL: u64 = 60;
 
 pub struct ZmqLoop {
-  pub socket: zmq::Socket,
-  pub insert_tx: mpsc::Sender<String>,
+  pub source_queue: Queue<(usize, String)>,
+  pub sources: Vec<SourceStats>,
+  pub insert_queue: Queue<String>,
+  pub node_queue: Queue<String>,
+  pub metrics: Arc<Metrics>,
+  pub recorder: Option<Recorder>,
+}
+
+#[derive(Debug)]
+pub struct SourceStats {
+  pub uri: String,
+  pub received: u64,
+  pub forwarded: u64,
+  pub duplicates: u64,
+  pub lag: f64,
+  pub last_received: Option<Instant>,
+}
+
+struct Dedup {
+  seen: HashMap<String, Instant>,
+  order: VecDeque<String>,
 }
 
 impl ZmqLoop {
-  pub fn run(self) -> ! {
-    loop {
-      match self.socket.recv_string(0) {
-        Ok(Ok(string)) => {
-          info!("{}", string);
-          self
-            .insert_tx
-            .send(string)
-            .expect("Thread communication failure");
-        }
-        Ok(Err(err)) => {
-          error!("Unexpected byte sequence: {:?}", err);
+  pub fn spawn(self) -> thread::JoinHandle<()> {
+    let thread = thread::Builder::new().name("zmq".into());
+    let thread = thread.spawn(move || self.run());
+    thread.expect("Thread spawn failure")
+  }
+
+  fn run(self) {
+    let Self {
+      source_queue,
+      mut sources,
+      insert_queue,
+      node_queue,
+      metrics,
+      mut recorder,
+    } = self;
+    let mut dedup = Dedup::new();
+    let mut stats_logged = Instant::now();
+    while let Some((index, string)) = source_queue.pop() {
+      let now = Instant::now();
+      metrics.received();
+      let duplicate = dedup.check(dedup_key(&string), now);
+      {
+        let source = &mut sources[index];
+        source.received += 1;
+        source.last_received = Some(now);
+        if let Some(first_received) = duplicate {
+          source.duplicates += 1;
+          source.lag += now.duration_since(first_received).as_milliseconds();
+        } else {
+          source.forwarded += 1;
         }
-        Err(err) => {
-          error!("{}", err);
+      }
+      if duplicate.is_some() {
+        debug!("#{} Duplicate: {}", index, string);
+      } else {
+        info!("#{} {}", index, string);
+        if let Some(ref mut recorder) = recorder {
+          if let Err(err) = recorder.record(&string) {
+            error!("Record failure: {}", err);
+          }
         }
+        dispatch(&insert_queue, &node_queue, string);
+      }
+      if stats_logged.elapsed() >= Duration::from_secs(STATS_INTERVAL) {
+        stats_logged = now;
+        log_stats(&sources, now);
+      }
+    }
+    log_stats(&sources, Instant::now());
+  }
+}
+
+impl SourceStats {
+  pub fn new(uri: &str) -> Self {
+    Self {
+      uri: uri.to_owned(),
+      received: 0,
+      forwarded: 0,
+      duplicates: 0,
+      lag: 0.0,
+      last_received: None,
+    }
+  }
+
+  pub fn average_lag(&self) -> f64 {
+    if self.duplicates > 0 {
+      self.lag / self.duplicates as f64
+    } else {
+      0.0
+    }
+  }
+}
+
+impl Dedup {
+  fn new() -> Self {
+    Self {
+      seen: HashMap::new(),
+      order: VecDeque::new(),
+    }
+  }
+
+  fn check(&mut self, key: &str, now: Instant) -> Option<Instant> {
+    if let Some(&first_received) = self.seen.get(key) {
+      return Some(first_received);
+    }
+    if self.order.len() >= DEDUP_CAPACITY {
+      if let Some(oldest) = self.order.pop_front() {
+        self.seen.remove(&oldest);
       }
     }
+    self.seen.insert(key.to_owned(), now);
+    self.order.push_back(key.to_owned());
+    None
+  }
+}
+
+pub fn dispatch(
+  insert_queue: &Queue<String>,
+  node_queue: &Queue<String>,
+  string: String,
+) {
+  let queue = match string.split(' ').next() {
+    Some("tx") | Some("tx_trytes") => insert_queue,
+    Some("lmi") | Some("lmsi") | Some("lmhs") | Some("sn") => node_queue,
+    _ => {
+      warn!("Unknown topic: {}", string);
+      return;
+    }
+  };
+  queue.push(string);
+}
+
+fn dedup_key(string: &str) -> &str {
+  let mut chunks = string.split(' ');
+  let hash = match chunks.next() {
+    Some("tx") => chunks.next(),
+    Some("tx_trytes") => chunks.nth(1),
+    _ => None,
+  };
+  hash.unwrap_or(string)
+}
+
+fn log_stats(sources: &[SourceStats], now: Instant) {
+  for (index, source) in sources.iter().enumerate() {
+    let idle = source
+      .last_received
+      .map(|last_received| now.duration_since(last_received).as_secs());
+    info!(
+      "#{} {} received: {}, forwarded: {}, duplicates: {}, \
+       average lag: {:.3}ms, idle: {}",
+      index,
+      source.uri,
+      source.received,
+      source.forwarded,
+      source.duplicates,
+      source.average_lag(),
+      idle.map_or_else(|| "never".to_owned(), |idle| format!("{}s", idle))
+    );
   }
 }
@@ -0,0 +1,94 @@
+use super::{Push, Queue, Result};
+use event;
+use std::i32;
+use std::sync::Arc;
+use std::thread;
+use std::time::SystemTime;
+use storage::{Connection, Storage};
+use utils::SystemTimeUtils;
+use zmq;
+
+const NODE_TOPICS: &[&str] = &["lmi", "lmsi", "lmhs", "sn"];
+
+#[derive(Clone)]
+pub struct ZmqSource {
+  pub index: usize,
+  pub uri: String,
+  pub ctx: zmq::Context,
+  pub trytes: bool,
+  pub timeout: u64,
+  pub storage: Arc<Storage>,
+  pub source_queue: Queue<(usize, String)>,
+}
+
+impl ZmqSource {
+  pub fn spawn(self) -> thread::JoinHandle<()> {
+    let Self {
+      index,
+      uri,
+      ctx,
+      trytes,
+      timeout,
+      storage,
+      source_queue,
+    } = self;
+    let mut conn = storage.connect();
+    let thread = thread::Builder::new().name(format!("zmq#{}", index));
+    let thread = thread.spawn(move || {
+      let mut socket = connect(&ctx, &uri, trytes, timeout)
+        .expect("ZMQ socket connect failure");
+      loop {
+        match socket.recv_string(0) {
+          Ok(Ok(string)) => {
+            if let Push::Closed = source_queue.push((index, string)) {
+              break;
+            }
+          }
+          Ok(Err(err)) => {
+            error!("#{} Unexpected byte sequence: {:?}", index, err);
+          }
+          Err(zmq::Error::EAGAIN) => {
+            warn!("#{} No messages for {}ms, resubscribing", index, timeout);
+            match connect(&ctx, &uri, trytes, timeout) {
+              Ok(new_socket) => socket = new_socket,
+              Err(err) => {
+                error!("#{} ZMQ reconnect failure: {}", index, err);
+              }
+            }
+            if let Err(err) = reconnect_event(&mut *conn) {
+              error!("#{} {}", index, err);
+            }
+          }
+          Err(err) => {
+            error!("#{} {}", index, err);
+          }
+        }
+      }
+    });
+    thread.expect("Thread spawn failure")
+  }
+}
+
+fn connect(
+  ctx: &zmq::Context,
+  uri: &str,
+  trytes: bool,
+  timeout: u64,
+) -> zmq::Result<zmq::Socket> {
+  let socket = ctx.socket(zmq::SUB)?;
+  if timeout > 0 {
+    socket.set_rcvtimeo(timeout.min(i32::MAX as u64) as i32)?;
+  }
+  socket.connect(uri)?;
+  let transaction_topic = if trytes { "tx_trytes" } else { "tx" };
+  for topic in Some(&transaction_topic).into_iter().chain(NODE_TOPICS) {
+    socket.set_subscribe(format!("{} ", topic).as_bytes())?;
+  }
+  Ok(socket)
+}
+
+fn reconnect_event(conn: &mut Connection) -> Result<()> {
+  let timestamp = SystemTime::milliseconds_since_epoch()?;
+  event::zmq_reconnect(conn, timestamp)?;
+  Ok(())
+}